@@ -0,0 +1,89 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use local_storage_vec::LocalStorageVec;
+use std::hint::black_box;
+
+const N: usize = 64;
+
+/// Compare [LocalStorageVec::push] against [Vec::push]
+/// 16 items stay inline
+/// 65 items spill to the heap exactly once, on the last push
+/// 1024 items spend most of their time on the heap
+fn bench_push(c: &mut Criterion) {
+    let mut group = c.benchmark_group("push");
+    for count in [16, N + 1, 1024].into_iter() {
+        group.bench_with_input(BenchmarkId::new("LocalStorageVec", count), &count, |b, &count| {
+            b.iter(|| {
+                let mut vec: LocalStorageVec<u32, N> = LocalStorageVec::new();
+                for i in 0..count as u32 {
+                    vec.push(black_box(i));
+                }
+                vec
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("Vec", count), &count, |b, &count| {
+            b.iter(|| {
+                let mut vec = Vec::new();
+                for i in 0..count as u32 {
+                    vec.push(black_box(i));
+                }
+                vec
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Compare iterating over a [LocalStorageVec] and a [Vec] by reference
+fn bench_iter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iter");
+    for count in [16, 1024].into_iter() {
+        let mut local: LocalStorageVec<u32, N> = LocalStorageVec::new();
+        for i in 0..count as u32 {
+            local.push(i);
+        }
+        let vec: Vec<u32> = (0..count as u32).collect();
+        group.bench_with_input(BenchmarkId::new("LocalStorageVec", count), &local, |b, local| {
+            b.iter(|| local.iter().copied().sum::<u32>())
+        });
+        group.bench_with_input(BenchmarkId::new("Vec", count), &vec, |b, vec| {
+            b.iter(|| vec.iter().copied().sum::<u32>())
+        });
+    }
+    group.finish();
+}
+
+/// Compare [LocalStorageVec::retain] against [Vec::retain], keeping every other item
+/// 16 items are compacted inline, 1024 items on the heap
+fn bench_retain(c: &mut Criterion) {
+    let mut group = c.benchmark_group("retain");
+    for count in [16, 1024].into_iter() {
+        group.bench_with_input(BenchmarkId::new("LocalStorageVec", count), &count, |b, &count| {
+            b.iter_batched(
+                || {
+                    let mut vec: LocalStorageVec<u32, N> = LocalStorageVec::new();
+                    vec.extend(0..count as u32);
+                    vec
+                },
+                |mut vec| {
+                    vec.retain(|x| black_box(x) % 2 == 0);
+                    vec
+                },
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("Vec", count), &count, |b, &count| {
+            b.iter_batched(
+                || (0..count as u32).collect::<Vec<_>>(),
+                |mut vec| {
+                    vec.retain(|x| black_box(x) % 2 == 0);
+                    vec
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_push, bench_iter, bench_retain);
+criterion_main!(benches);
@@ -1,36 +1,109 @@
+#![cfg_attr(feature = "nightly", feature(extend_one))]
+
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::ops::{Bound, Range};
+use std::ptr;
+
 /// A growable, generic list that resides on the stack if it's small,
 /// but is moved to the heap to grow larger if needed.
 /// This list is generic over the items it contains as well as the
 /// size of its buffer if it's on the stack.
+/// With `N == 0` there is no room on the stack at all,
+/// so the first element pushed moves the list to the heap.
+///
+/// On the stack, only the first `len` slots of `buf` are initialized,
+/// so `T` needs neither `Copy` nor `Default`.
 pub enum LocalStorageVec<T, const N: usize> {
-    Stack { buf: [T; N], len: usize },
+    Stack { buf: [MaybeUninit<T>; N], len: usize },
     Heap(Vec<T>),
 }
 
+impl<T, const N: usize> Drop for LocalStorageVec<T, N> {
+    fn drop(&mut self) {
+        if let Self::Stack { buf, len } = self {
+            // SAFETY: the first `len` slots are initialized
+            unsafe { ptr::drop_in_place(init_slice_mut(&mut buf[..*len])) };
+        }
+    }
+}
+
+/// Views initialized slots as a slice of `T`.
+///
+/// # Safety
+///
+/// Every slot in `slots` must be initialized.
+unsafe fn init_slice<T>(slots: &[MaybeUninit<T>]) -> &[T] {
+    unsafe { &*(slots as *const [MaybeUninit<T>] as *const [T]) }
+}
+
+/// Views initialized slots as a mutable slice of `T`.
+///
+/// # Safety
+///
+/// Every slot in `slots` must be initialized.
+unsafe fn init_slice_mut<T>(slots: &mut [MaybeUninit<T>]) -> &mut [T] {
+    unsafe { &mut *(slots as *mut [MaybeUninit<T>] as *mut [T]) }
+}
+
 impl<T, const N: usize> From<Vec<T>> for LocalStorageVec<T, N> {
     fn from(v: Vec<T>) -> Self {
         Self::Heap(v)
     }
 }
 
-impl<T: Default, const N: usize, const M: usize> From<[T; N]> for LocalStorageVec<T, M> {
+impl<T, const N: usize, const M: usize> From<[T; N]> for LocalStorageVec<T, M> {
     fn from(array: [T; N]) -> Self {
         if N <= M {
-            let mut it = array.into_iter();
-            Self::Stack {
-                buf: [(); M].map(|_| it.next().unwrap_or_default()),
-                len: N,
+            let mut buf = [const { MaybeUninit::uninit() }; M];
+            for (slot, item) in buf.iter_mut().zip(array) {
+                slot.write(item);
             }
+            Self::Stack { buf, len: N }
         } else {
             Self::Heap(Vec::from(array))
         }
     }
 }
 
-impl<T: Copy + Default, const N: usize> LocalStorageVec<T, N> {
+/// Clones onto the stack if `self` is on the stack, and onto the heap otherwise.
+/// Only the first `len` elements are cloned; the unused stack slots stay
+/// uninitialized and are never read, so `T` need not be [Default].
+impl<T: Clone, const N: usize> Clone for LocalStorageVec<T, N> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Stack { .. } => self.iter().cloned().collect(),
+            Self::Heap(v) => Self::Heap(v.clone()),
+        }
+    }
+}
+
+/// Compares the elements, regardless of where they are stored
+impl<T: PartialEq, const N: usize> PartialEq for LocalStorageVec<T, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_ref() == other.as_ref()
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for LocalStorageVec<T, N> {}
+
+impl<T: std::fmt::Debug, const N: usize> std::fmt::Debug for LocalStorageVec<T, N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Concatenates the chars, e.g. for text built up inline
+impl<const N: usize> std::fmt::Display for LocalStorageVec<char, N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use std::fmt::Write;
+        self.iter().try_for_each(|&c| f.write_char(c))
+    }
+}
+
+impl<T, const N: usize> LocalStorageVec<T, N> {
     pub fn new() -> Self {
         Self::Stack {
-            buf: [T::default(); N],
+            buf: [const { MaybeUninit::uninit() }; N],
             len: 0,
         }
     }
@@ -42,19 +115,148 @@ impl<T: Copy + Default, const N: usize> LocalStorageVec<T, N> {
         }
     }
 
+    /// How many more elements fit on the stack, or `None` once on the heap.
+    pub fn capacity_remaining(&self) -> Option<usize> {
+        match self {
+            Self::Stack { len, .. } => Some(N - len),
+            Self::Heap(_) => None,
+        }
+    }
+
     pub fn push(&mut self, item: T) {
         match self {
             Self::Stack { buf, len } if *len < N => {
-                buf[*len] = item;
+                buf[*len].write(item);
                 *len += 1;
             }
-            _ => {
-                let mut v = match std::mem::replace(self, Self::Heap(Vec::new())) {
-                    Self::Stack { buf, len } => buf[..len].to_vec(),
-                    Self::Heap(v) => v,
-                };
+            // Spill once, with room to grow, so the next pushes
+            // don't immediately reallocate the new heap buffer.
+            Self::Stack { .. } => {
+                self.reserve(1);
+                self.push(item);
+            }
+            Self::Heap(v) => v.push(item),
+        }
+    }
+
+    /// Moves the elements to the heap, with room for `additional` more,
+    /// and returns the heap `Vec`. Does nothing if already on the heap.
+    fn spill(&mut self, additional: usize) -> &mut Vec<T> {
+        if let Self::Stack { buf, len } = self {
+            let mut v = Vec::with_capacity(*len + additional);
+            for slot in &buf[..*len] {
+                // SAFETY: the first `len` slots are initialized, and `len` is
+                // reset below so they are not dropped again
+                v.push(unsafe { slot.assume_init_read() });
+            }
+            *len = 0;
+            // The moved-out elements may be secrets, see [SecretLocalStorageVec]
+            #[cfg(feature = "zeroize")]
+            zeroize::Zeroize::zeroize(&mut buf[..]);
+            *self = Self::Heap(v);
+            #[cfg(feature = "metrics")]
+            metrics::SPILLS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+        match self {
+            Self::Heap(v) => v,
+            Self::Stack { .. } => unreachable!(),
+        }
+    }
+
+    /// Pushes `item` as into a ring buffer of size `N`: once the stack is full,
+    /// the oldest element is evicted and returned instead of spilling to the heap.
+    /// A vec that is already on the heap keeps its length the same way.
+    pub fn push_ring(&mut self, item: T) -> Option<T> {
+        match self {
+            Self::Stack { .. } if N == 0 => Some(item),
+            Self::Stack { buf, len } if *len == N => {
+                // SAFETY: the stack is full, so every slot is initialized.
+                // After rotating, the last slot holds a stale copy of `oldest`
+                // that is overwritten without being dropped.
+                let oldest = unsafe { buf[0].assume_init_read() };
+                buf.rotate_left(1);
+                buf[N - 1].write(item);
+                Some(oldest)
+            }
+            Self::Heap(v) if !v.is_empty() => {
+                let oldest = v.remove(0);
                 v.push(item);
-                *self = Self::Heap(v);
+                Some(oldest)
+            }
+            _ => {
+                self.push(item);
+                None
+            }
+        }
+    }
+
+    /// Pushes `item` unless an equal element is already present,
+    /// and returns whether it was pushed.
+    pub fn insert_unique(&mut self, item: T) -> bool
+    where
+        T: PartialEq,
+    {
+        if self.contains(&item) {
+            return false;
+        }
+        self.push(item);
+        true
+    }
+
+    /// The heap capacity chosen when spilling to make room for `needed` elements:
+    /// the next power of two, so that growth from the stack onwards doubles
+    /// like `Vec`'s own and repeated pushes reallocate only `O(log n)` times.
+    pub fn grown_capacity(needed: usize) -> usize {
+        needed.next_power_of_two()
+    }
+
+    /// Makes room for at least `additional` more elements, moving to the heap
+    /// with [LocalStorageVec::grown_capacity] if they don't fit on the stack.
+    pub fn reserve(&mut self, additional: usize) {
+        match self {
+            Self::Stack { len, .. } if *len + additional <= N => {}
+            Self::Stack { len, .. } => {
+                let len = *len;
+                self.spill(Self::grown_capacity(len + additional) - len);
+            }
+            Self::Heap(v) => v.reserve(additional),
+        }
+    }
+
+    /// Makes room for exactly `additional` more elements,
+    /// moving to the heap if they don't fit on the stack.
+    pub fn reserve_exact(&mut self, additional: usize) {
+        match self {
+            Self::Stack { len, .. } if *len + additional <= N => {}
+            Self::Stack { .. } => {
+                self.spill(additional);
+            }
+            Self::Heap(v) => v.reserve_exact(additional),
+        }
+    }
+
+    /// Moves the elements back onto the stack if they fit there,
+    /// otherwise shrinks the heap allocation to the length.
+    pub fn shrink_to_fit(&mut self) {
+        self.shrink_to_stack();
+        if let Self::Heap(v) = self {
+            v.shrink_to_fit();
+        }
+    }
+
+    /// Moves the elements from the heap back onto the stack and frees
+    /// the heap allocation, if they fit there. Does nothing otherwise.
+    pub fn shrink_to_stack(&mut self) {
+        if let Self::Heap(v) = self {
+            if v.len() <= N {
+                let len = v.len();
+                let mut buf = [const { MaybeUninit::uninit() }; N];
+                for (slot, item) in buf.iter_mut().zip(v.drain(..)) {
+                    slot.write(item);
+                }
+                *self = Self::Stack { buf, len };
+                #[cfg(feature = "metrics")]
+                metrics::SHRINKS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
             }
         }
     }
@@ -63,377 +265,2350 @@ impl<T: Copy + Default, const N: usize> LocalStorageVec<T, N> {
         match self {
             Self::Stack { buf, len } if *len > 0 => {
                 *len -= 1;
-                Some(buf[*len])
+                // SAFETY: the slot was initialized and is now past `len`
+                Some(unsafe { buf[*len].assume_init_read() })
             }
             Self::Heap(v) => v.pop(),
             _ => None,
         }
     }
 
+    /// Pops the last element only if it satisfies `pred`.
+    pub fn pop_if<F: FnOnce(&T) -> bool>(&mut self, pred: F) -> Option<T> {
+        if pred(self.peek_last()?) {
+            self.pop()
+        } else {
+            None
+        }
+    }
+
     pub fn insert(&mut self, index: usize, item: T) {
+        let len = self.len();
+        assert!(index <= len, "insertion index (is {index}) should be <= len (is {len})");
         match self {
             Self::Stack { buf, len } if *len < N => {
-                buf.copy_within(index..*len, index + 1);
-                buf[index] = item;
+                buf[index..=*len].rotate_right(1);
+                buf[index].write(item);
                 *len += 1;
             }
+            _ => self.spill(1).insert(index, item),
+        }
+    }
+
+    /// Inserts all of `items` at `index`, shifting the tail only once.
+    pub fn insert_slice(&mut self, index: usize, items: &[T])
+    where
+        T: Copy,
+    {
+        let len = self.len();
+        assert!(index <= len, "insertion index (is {index}) should be <= len (is {len})");
+        match self {
+            Self::Stack { buf, len } if *len + items.len() <= N => {
+                buf[index..*len + items.len()].rotate_right(items.len());
+                for (slot, &item) in buf[index..].iter_mut().zip(items) {
+                    slot.write(item);
+                }
+                *len += items.len();
+            }
             _ => {
-                let mut v = match std::mem::replace(self, Self::Heap(Vec::new())) {
-                    Self::Stack { buf, len } => buf[..len].to_vec(),
-                    Self::Heap(v) => v,
-                };
-                v.insert(index, item);
-                *self = Self::Heap(v);
+                self.spill(items.len()).splice(index..index, items.iter().copied());
             }
         }
     }
 
     pub fn remove(&mut self, index: usize) -> T {
+        let len = self.len();
+        assert!(index < len, "removal index (is {index}) should be < len (is {len})");
         match self {
-            Self::Stack { buf, len } if *len > 0 => {
-                let item = buf[index];
-                buf.copy_within(index + 1..*len, index);
+            Self::Stack { buf, len } => {
+                // SAFETY: `index < len`, and the stale copy it leaves behind
+                // is rotated past the new `len`
+                let item = unsafe { buf[index].assume_init_read() };
+                buf[index..*len].rotate_left(1);
                 *len -= 1;
                 item
             }
             Self::Heap(v) => v.remove(index),
-            _ => panic!("Index out of bounds"),
         }
     }
 
-    pub fn clear(&mut self) {
-        match self {
-            Self::Stack { len, .. } => *len = 0,
-            Self::Heap(v) => v.clear(),
-        }
+    /// Puts `value` at `index` and returns the element that was there.
+    pub fn replace(&mut self, index: usize, value: T) -> T {
+        let len = self.len();
+        assert!(index < len, "replacement index (is {index}) should be < len (is {len})");
+        std::mem::replace(&mut self.as_mut()[index], value)
     }
-}
 
-pub struct LocalStorageVecIter<T, const N: usize> {
-    vec: LocalStorageVec<T, N>,
-    counter: usize,
-}
-
-impl<T: Default, const N: usize> Iterator for LocalStorageVecIter<T, N> {
-    type Item = T;
+    /// Removes the elements at all given indices in a single pass.
+    /// The indices may be unsorted and contain duplicates.
+    pub fn remove_all(&mut self, indices: &[usize]) {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        let len = self.len();
+        if let Some(&last) = indices.last() {
+            assert!(last < len, "removal index (is {last}) should be < len (is {len})");
+        }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.counter < self.vec.len() {
-            let item = std::mem::take(&mut self.vec[self.counter]);
-            self.counter += 1;
-            Some(item)
-        } else {
-            None
+        let items = self.as_mut();
+        let mut to_remove = indices.into_iter().peekable();
+        let mut kept = 0;
+        for i in 0..len {
+            if to_remove.next_if_eq(&i).is_none() {
+                items.swap(kept, i);
+                kept += 1;
+            }
         }
+        self.truncate(kept);
     }
-}
-use std::ops::IndexMut;
 
-impl<T, const N: usize> IndexMut<usize> for LocalStorageVec<T, N> {
-    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        match self {
-            LocalStorageVec::Stack { buf, len } if index < *len => &mut buf[index],
-            LocalStorageVec::Heap(v) => &mut v[index],
-            _ => panic!("Index out of bounds"),
+    /// Shortens the vec to `new_len` elements, dropping the rest
+    /// from the last one inward. Does nothing if the vec is not longer than that.
+    pub fn truncate(&mut self, new_len: usize) {
+        while self.len() > new_len {
+            drop(self.pop());
         }
     }
-}
-impl<T: Default, const N: usize> IntoIterator for LocalStorageVec<T, N> {
-    type Item = T;
-    type IntoIter = LocalStorageVecIter<T, N>;
 
-    fn into_iter(self) -> Self::IntoIter {
-        LocalStorageVecIter {
-            vec: self,
-            counter: 0,
-        }
+    pub fn clear(&mut self) {
+        self.truncate(0);
     }
-}
 
-use std::ops::{Index, Range, RangeFrom, RangeTo};
+    /// Empties the vec and returns an iterator over its former elements.
+    /// The vec is left empty on the stack; the elements are moved out
+    /// of their original storage rather than copied into a new `Vec`.
+    pub fn clear_and_drain(&mut self) -> impl Iterator<Item = T> {
+        std::mem::replace(self, Self::new()).into_iter()
+    }
 
-impl<T, const N: usize> Index<usize> for LocalStorageVec<T, N> {
-    type Output = T;
+    /// Removes and yields all elements like [LocalStorageVec::clear_and_drain],
+    /// but a heap vec stays on the heap and keeps its allocation for reuse.
+    /// Elements not yet yielded are dropped along with the iterator.
+    pub fn drain_all(&mut self) -> impl Iterator<Item = T> + '_ {
+        // Only one of these is `Some`, depending on where the elements are stored
+        let (stack, heap) = match self {
+            Self::Stack { .. } => (Some(std::mem::replace(self, Self::new()).into_iter()), None),
+            Self::Heap(v) => (None, Some(v.drain(..))),
+        };
+        stack.into_iter().flatten().chain(heap.into_iter().flatten())
+    }
 
-    fn index(&self, index: usize) -> &Self::Output {
+    /// Removes and returns the elements `[0, at)`, keeping `[at, len)`.
+    pub fn split_to(&mut self, at: usize) -> Self {
+        let len = self.len();
+        assert!(at <= len, "`at` split index (is {at}) should be <= len (is {len})");
         match self {
-            Self::Stack { buf, len } if index < *len => &buf[index],
-            Self::Heap(v) => &v[index],
-            _ => panic!("Index out of bounds"),
+            Self::Stack { buf, len } => {
+                let mut front = [const { MaybeUninit::uninit() }; N];
+                front[..at].swap_with_slice(&mut buf[..at]);
+                buf[..*len].rotate_left(at);
+                *len -= at;
+                Self::Stack { buf: front, len: at }
+            }
+            Self::Heap(v) => {
+                let back = v.split_off(at);
+                Self::Heap(std::mem::replace(v, back))
+            }
         }
     }
-}
-
-impl<T, const N: usize> Index<RangeTo<usize>> for LocalStorageVec<T, N> {
-    type Output = [T];
 
-    fn index(&self, index: RangeTo<usize>) -> &Self::Output {
+    /// Removes and returns the elements `[at, len)`, keeping `[0, at)`.
+    /// When splitting a heap vec, each half that fits is moved back onto the stack.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        let len = self.len();
+        assert!(at <= len, "`at` split index (is {at}) should be <= len (is {len})");
         match self {
-            Self::Stack { buf, len } if index.end <= *len => &buf[..index.end],
-            Self::Heap(v) => &v[..index.end],
-            _ => panic!("Index out of bounds"),
+            Self::Stack { buf, len } => {
+                let mut back = [const { MaybeUninit::uninit() }; N];
+                back[..*len - at].swap_with_slice(&mut buf[at..*len]);
+                let back_len = *len - at;
+                *len = at;
+                Self::Stack { buf: back, len: back_len }
+            }
+            Self::Heap(v) => {
+                let mut back = Self::Heap(v.split_off(at));
+                back.shrink_to_stack();
+                self.shrink_to_stack();
+                back
+            }
         }
     }
-}
 
-impl<T, const N: usize> Index<RangeFrom<usize>> for LocalStorageVec<T, N> {
-    type Output = [T];
+    /// Moves all elements of `other` to the end, leaving `other` empty.
+    /// Spills to the heap at once if the combined elements don't fit on the stack.
+    pub fn append(&mut self, other: &mut Self) {
+        let other = std::mem::replace(other, Self::new());
+        self.reserve_exact(other.len());
+        self.extend(other);
+    }
 
-    fn index(&self, index: RangeFrom<usize>) -> &Self::Output {
-        match self {
-            Self::Stack { buf, len } if index.start < *len => &buf[index.start..*len],
-            Self::Heap(v) => &v[index.start..],
-            _ => panic!("Index out of bounds"),
+    /// Appends the elements of a vec with a possibly different inline capacity,
+    /// consuming it, without converting through a `Vec`.
+    pub fn extend_from_lsv<const M: usize>(&mut self, other: LocalStorageVec<T, M>) {
+        self.reserve_exact(other.len());
+        self.extend(other);
+    }
+
+    /// Moves all elements to the end of `target`, consuming `self`.
+    /// Unlike [LocalStorageVec::append], a heap buffer moved into an empty stack
+    /// `target` is taken over instead of copied.
+    pub fn move_into(self, target: &mut Self) {
+        if let (Self::Stack { len: 0, .. }, Self::Heap(_)) = (&*target, &self) {
+            *target = self;
+            return;
         }
+        target.reserve_exact(self.len());
+        target.extend(self);
     }
-}
 
-impl<T, const N: usize> Index<Range<usize>> for LocalStorageVec<T, N> {
-    type Output = [T];
+    /// Splits the elements into owned vecs of `size` elements each, the last one
+    /// possibly shorter. Each chunk is stored on the stack if it fits there.
+    pub fn into_chunks(self, size: usize) -> impl Iterator<Item = Self> {
+        assert!(size != 0, "chunk size must be non-zero");
+        let mut items = self.into_iter().peekable();
+        std::iter::from_fn(move || {
+            items.peek()?;
+            Some(items.by_ref().take(size).collect())
+        })
+    }
 
-    fn index(&self, index: Range<usize>) -> &Self::Output {
-        match self {
-            Self::Stack { buf, len } if index.end <= *len => &buf[index],
-            Self::Heap(v) => &v[index],
-            _ => panic!("Index out of bounds"),
+    /// Keeps the elements matching `pred` and returns the others.
+    /// Both halves are stored on the stack if they fit.
+    pub fn partition<F: FnMut(&T) -> bool>(&mut self, mut pred: F) -> Self {
+        let mut rest = Self::new();
+        for item in std::mem::replace(self, Self::new()) {
+            if pred(&item) {
+                self.push(item);
+            } else {
+                rest.push(item);
+            }
         }
+        rest
     }
-}
 
-use std::ops::{Deref, DerefMut};
+    /// Takes the vec apart into its backing storage without copying:
+    /// `Ok` with the `Vec` if it is on the heap,
+    /// `Err` with the inline buffer and length if it is on the stack.
+    /// Only the first `len` slots of the buffer are initialized,
+    /// and it is up to the caller to drop them.
+    pub fn into_parts(self) -> Result<Vec<T>, ([MaybeUninit<T>; N], usize)> {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never used or dropped again,
+        // so the storage is moved out exactly once
+        match &*this {
+            Self::Stack { buf, len } => Err((unsafe { ptr::read(buf) }, *len)),
+            Self::Heap(v) => Ok(unsafe { ptr::read(v) }),
+        }
+    }
 
-impl<T, const N: usize> Deref for LocalStorageVec<T, N> {
-    type Target = [T];
+    /// Reassembles a vec from the parts returned by [LocalStorageVec::into_parts].
+    ///
+    /// # Safety
+    ///
+    /// For `Err((buf, len))`, the first `len` slots of `buf` must be initialized.
+    pub unsafe fn from_parts(parts: Result<Vec<T>, ([MaybeUninit<T>; N], usize)>) -> Self {
+        match parts {
+            Ok(v) => Self::Heap(v),
+            Err((buf, len)) => {
+                assert!(len <= N, "len (is {len}) should be <= N (is {N})");
+                Self::Stack { buf, len }
+            }
+        }
+    }
 
-    fn deref(&self) -> &Self::Target {
-        <Self as AsRef<[T]>>::as_ref(self)
+    /// Moves the elements into an array if there are exactly `M` of them,
+    /// otherwise gives back the vec unchanged.
+    pub fn collect_array<const M: usize>(self) -> Result<[T; M], Self> {
+        if self.len() != M {
+            return Err(self);
+        }
+        match self.into_parts() {
+            Ok(v) => v.try_into().map_err(Self::Heap),
+            // SAFETY: all `M` elements are initialized and read exactly once
+            Err((buf, _)) => Ok(std::array::from_fn(|i| unsafe { buf[i].assume_init_read() })),
+        }
     }
-}
-use std::convert::AsRef;
 
-impl<T, const N: usize> AsRef<[T]> for LocalStorageVec<T, N> {
-    fn as_ref(&self) -> &[T] {
+    /// The whole inline buffer as an array, if the vec is on the stack
+    /// and exactly full, otherwise `None`.
+    pub fn as_full_array(&self) -> Option<&[T; N]> {
         match self {
-            LocalStorageVec::Stack { buf, len } => &buf[..*len],
-            LocalStorageVec::Heap(v) => v.as_ref(),
+            // SAFETY: with `len == N` every slot is initialized
+            Self::Stack { buf, len } if *len == N => Some(unsafe { &*buf.as_ptr().cast::<[T; N]>() }),
+            _ => None,
         }
     }
-}
-use std::convert::AsMut;
 
-impl<T, const N: usize> AsMut<[T]> for LocalStorageVec<T, N> {
-    fn as_mut(&mut self) -> &mut [T] {
+    /// Returns mutable references to the elements at all `indices` at once,
+    /// or `None` if any index is out of bounds or occurs more than once.
+    pub fn get_many_mut<const K: usize>(&mut self, indices: [usize; K]) -> Option<[&mut T; K]> {
+        self.as_mut().get_disjoint_mut(indices).ok()
+    }
+
+    /// A pointer to the start of the backing storage, the inline buffer
+    /// or the heap allocation, valid for writes up to its full capacity.
+    pub fn as_mut_ptr(&mut self) -> *mut T {
         match self {
-            LocalStorageVec::Stack { buf, len } => &mut buf[..*len],
-            LocalStorageVec::Heap(v) => v.as_mut(),
+            Self::Stack { buf, .. } => buf.as_mut_ptr().cast(),
+            Self::Heap(v) => v.as_mut_ptr(),
         }
     }
-}
-impl<T, const N: usize> DerefMut for LocalStorageVec<T, N> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        <Self as AsMut<[T]>>::as_mut(self)
+
+    /// Sets the length without touching the elements, to commit elements
+    /// written through [LocalStorageVec::as_mut_ptr].
+    /// On the stack, `new_len` is clamped to `N`.
+    ///
+    /// # Safety
+    ///
+    /// The elements at `old_len..new_len` must be initialized,
+    /// and on the heap `new_len` must be at most the capacity of the `Vec`.
+    /// Elements at `new_len..old_len` are forgotten rather than dropped.
+    pub unsafe fn set_len(&mut self, new_len: usize) {
+        match self {
+            Self::Stack { len, .. } => *len = new_len.min(N),
+            // SAFETY: upheld by the caller
+            Self::Heap(v) => unsafe { v.set_len(new_len) },
+        }
     }
-}
 
-#[cfg(test)]
-mod test {
-    use crate::LocalStorageVec;
+    /// The most recently pushed element, the same as [slice::last].
+    pub fn peek_last(&self) -> Option<&T> {
+        self.last()
+    }
 
+    /// A mutable reference to the most recently pushed element.
+    pub fn peek_last_mut(&mut self) -> Option<&mut T> {
+        self.last_mut()
+    }
 
+    /// Calls `f` with the index of and a mutable reference to every element.
+    pub fn for_each_mut<F: FnMut(usize, &mut T)>(&mut self, mut f: F) {
+        for (i, item) in self.iter_mut().enumerate() {
+            f(i, item);
+        }
+    }
 
-    #[test]
-    fn it_from_vecs() {
-        let vec: LocalStorageVec<usize, 10> = LocalStorageVec::from(vec![1, 2, 3]);
-        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+    /// The smallest element, or `None` if the vec is empty.
+    pub fn min(&self) -> Option<&T>
+    where
+        T: Ord,
+    {
+        self.iter().min()
+    }
 
-        let vec: LocalStorageVec<usize, 2> = LocalStorageVec::from(vec![1, 2, 3]);
-        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+    /// The largest element, or `None` if the vec is empty.
+    pub fn max(&self) -> Option<&T>
+    where
+        T: Ord,
+    {
+        self.iter().max()
     }
 
+    /// The element for which `f` returns the smallest key,
+    /// the first one on ties.
+    pub fn min_by_key<K: Ord, F: FnMut(&T) -> K>(&self, mut f: F) -> Option<&T> {
+        self.iter().min_by_key(|item| f(item))
+    }
 
-    #[test]
-    fn it_as_refs() {
-        let vec: LocalStorageVec<i32, 256> = LocalStorageVec::from([0; 128]);
-        let slice: &[i32] = vec.as_ref();
-        assert!(slice.len() == 128);
-        let vec: LocalStorageVec<i32, 32> = LocalStorageVec::from([0; 128]);
-        let slice: &[i32] = vec.as_ref();
-        assert!(slice.len() == 128);
+    /// The element for which `f` returns the largest key,
+    /// the last one on ties.
+    pub fn max_by_key<K: Ord, F: FnMut(&T) -> K>(&self, mut f: F) -> Option<&T> {
+        self.iter().max_by_key(|item| f(item))
+    }
 
-        let mut vec: LocalStorageVec<i32, 256> = LocalStorageVec::from([0; 128]);
-        let slice_mut: &[i32] = vec.as_mut();
-        assert!(slice_mut.len() == 128);
-        let mut vec: LocalStorageVec<i32, 32> = LocalStorageVec::from([0; 128]);
-        let slice_mut: &[i32] = vec.as_mut();
-        assert!(slice_mut.len() == 128);
+    /// Calls `f` on every overlapping window of `size` elements in turn,
+    /// the mutable counterpart of [slice::windows].
+    /// Each window sees the changes made through the previous ones.
+    pub fn for_each_window_mut<F: FnMut(&mut [T])>(&mut self, size: usize, mut f: F) {
+        assert!(size != 0, "window size must be non-zero");
+        let items = self.as_mut();
+        for start in 0..(items.len() + 1).saturating_sub(size) {
+            f(&mut items[start..start + size]);
+        }
     }
 
+    /// The elements between an arbitrary pair of bounds.
+    /// Panics like slice indexing if the bounds are out of order or past `len`.
+    pub fn slice_bounds(&self, start: Bound<usize>, end: Bound<usize>) -> &[T] {
+        &self.as_ref()[(start, end)]
+    }
 
-    #[test]
-    fn it_constructs() {
-        let vec: LocalStorageVec<usize, 10> = LocalStorageVec::new();
-        assert!(matches!(vec, LocalStorageVec::Stack { buf: _, len: 0 }));
+    /// Iterates over every `step`-th element, starting with the first.
+    pub fn stride(&self, step: usize) -> impl Iterator<Item = &T> {
+        assert!(step != 0, "step must be non-zero");
+        self.iter().step_by(step)
     }
 
+    /// The index of the first element matching `pred`.
+    pub fn position<P: FnMut(&T) -> bool>(&self, pred: P) -> Option<usize> {
+        self.iter().position(pred)
+    }
 
-    #[test]
-    fn it_lens() {
-        let vec: LocalStorageVec<_, 3> = LocalStorageVec::from([0, 1, 2]);
-        assert_eq!(vec.len(), 3);
-        let vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2]);
-        assert_eq!(vec.len(), 3);
+    /// The index of the last element matching `pred`, scanning from the back.
+    pub fn rposition<P: FnMut(&T) -> bool>(&self, pred: P) -> Option<usize> {
+        self.iter().rposition(pred)
     }
 
+    /// Keeps only the elements for which `f` returns `true`.
+    /// Kept elements are moved to the front without copying,
+    /// removed elements are dropped immediately.
+    ///
+    /// If `f` panics, the elements it has not seen yet are kept,
+    /// just like with [Vec::retain].
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        /// Moves the unprocessed elements down over the gap left by the removed ones
+        /// and fixes up the length, also when `f` panics
+        struct Guard<'a, T> {
+            buf: &'a mut [MaybeUninit<T>],
+            len: &'a mut usize,
+            processed: usize,
+            removed: usize,
+        }
 
-    #[test]
-    fn it_pushes() {
-        let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::new();
-        for value in 0..128 {
-            vec.push(value);
+        impl<T> Drop for Guard<'_, T> {
+            fn drop(&mut self) {
+                let tail = *self.len - self.processed;
+                // SAFETY: the slots `[processed, len)` are initialized and are moved
+                // down into the gap, which holds no initialized elements
+                unsafe {
+                    let base = self.buf.as_mut_ptr();
+                    ptr::copy(base.add(self.processed), base.add(self.processed - self.removed), tail);
+                }
+                *self.len -= self.removed;
+            }
         }
-        assert!(matches!(vec, LocalStorageVec::Stack { len: 128, .. }));
-        for value in 128..256 {
-            vec.push(value);
+
+        match self {
+            Self::Stack { buf, len } => {
+                let mut guard = Guard {
+                    buf,
+                    len,
+                    processed: 0,
+                    removed: 0,
+                };
+                while guard.processed < *guard.len {
+                    let slot = guard.buf[guard.processed].as_mut_ptr();
+                    // SAFETY: slots below `len` that are not yet processed are initialized
+                    if f(unsafe { &*slot }) {
+                        if guard.removed > 0 {
+                            let gap = guard.buf[guard.processed - guard.removed].as_mut_ptr();
+                            // SAFETY: the gap slot was moved out of or dropped before
+                            unsafe { ptr::copy_nonoverlapping(slot, gap, 1) };
+                        }
+                        guard.processed += 1;
+                    } else {
+                        // Count the element as removed before dropping it,
+                        // so a panicking destructor does not cause a double drop
+                        guard.processed += 1;
+                        guard.removed += 1;
+                        // SAFETY: the slot is initialized and now counts as part of the gap
+                        unsafe { ptr::drop_in_place(slot) };
+                    }
+                }
+            }
+            Self::Heap(v) => v.retain(f),
         }
-        assert!(matches!(vec, LocalStorageVec::Heap(v) if v.len() == 256))
     }
 
+    /// Removes the trailing elements matching `pred`, scanning from the back,
+    /// and returns how many were removed.
+    pub fn truncate_while<F: FnMut(&T) -> bool>(&mut self, mut pred: F) -> usize {
+        let removed = self.iter().rev().take_while(|item| pred(item)).count();
+        self.truncate(self.len() - removed);
+        removed
+    }
 
-    #[test]
-    fn it_pops() {
-        let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 128]);
-        for _ in 0..128 {
-            assert_eq!(vec.pop(), Some(0))
+    /// Removes consecutive repeated elements.
+    pub fn dedup(&mut self)
+    where
+        T: PartialEq,
+    {
+        match self {
+            Self::Stack { .. } => {
+                let items = self.as_mut();
+                let mut kept = items.len().min(1);
+                for i in 1..items.len() {
+                    if items[i] != items[kept - 1] {
+                        items.swap(kept, i);
+                        kept += 1;
+                    }
+                }
+                self.truncate(kept);
+            }
+            Self::Heap(v) => v.dedup(),
         }
-        assert_eq!(vec.pop(), None);
+    }
 
-        let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 256]);
-        for _ in 0..256 {
-            assert_eq!(vec.pop(), Some(0))
-        }
-        assert_eq!(vec.pop(), None);
+    /// Sorts the elements and removes all duplicates.
+    pub fn sort_dedup(&mut self)
+    where
+        T: Ord,
+    {
+        self.sort();
+        self.dedup();
+    }
+}
 
-        let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::from(vec![0; 256]);
-        for _ in 0..256 {
-            assert_eq!(vec.pop(), Some(0))
+impl<T: Default, const N: usize> LocalStorageVec<T, N> {
+    /// Replaces every element by the result of `f`,
+    /// dropping those for which it returns `None`.
+    pub fn filter_map_in_place<F: FnMut(T) -> Option<T>>(&mut self, mut f: F) {
+        let items = self.as_mut();
+        let mut kept = 0;
+        for i in 0..items.len() {
+            if let Some(item) = f(std::mem::take(&mut items[i])) {
+                items[kept] = item;
+                kept += 1;
+            }
+        }
+        self.truncate(kept);
+    }
+
+    /// Overwrites every element with `T::default()`, keeping the length.
+    pub fn fill_with_default(&mut self)
+    where
+        T: Clone,
+    {
+        self.as_mut().fill(T::default());
+    }
+}
+
+impl<T: Clone, const N: usize> LocalStorageVec<T, N> {
+    /// Replaces the contents of `target` with clones of the elements,
+    /// reusing its allocation. Named so that it doesn't shadow
+    /// `<[T]>::clone_into`, which stays reachable through `Deref`.
+    pub fn clone_into_vec(&self, target: &mut Vec<T>) {
+        target.clear();
+        target.extend_from_slice(self);
+    }
+}
+
+impl<T: PartialEq, const N: usize> LocalStorageVec<T, N> {
+    /// Returns the index of the first occurrence of `needle`,
+    /// or `None` if it does not occur. An empty needle matches at 0.
+    pub fn find_subslice(&self, needle: &[T]) -> Option<usize> {
+        if needle.is_empty() {
+            return Some(0);
+        }
+        self.as_ref()
+            .windows(needle.len())
+            .position(|window| window == needle)
+    }
+
+    /// Encodes runs of equal consecutive elements as (value, run length) pairs.
+    pub fn run_length_encode(&self) -> Vec<(T, usize)>
+    where
+        T: Clone,
+    {
+        self.chunk_by(|a, b| a == b)
+            .map(|run| (run[0].clone(), run.len()))
+            .collect()
+    }
+}
+
+/// Process-wide counters of how often vecs move between the stack and the heap,
+/// to help pick `N` for a real workload
+#[cfg(feature = "metrics")]
+mod metrics {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    pub(crate) static SPILLS: AtomicUsize = AtomicUsize::new(0);
+    pub(crate) static SHRINKS: AtomicUsize = AtomicUsize::new(0);
+
+    /// How often any vec has spilled from the stack to the heap
+    pub fn spill_count() -> usize {
+        SPILLS.load(Ordering::Relaxed)
+    }
+
+    /// How often any vec has moved from the heap back onto the stack
+    pub fn shrink_count() -> usize {
+        SHRINKS.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(feature = "metrics")]
+pub use metrics::{shrink_count, spill_count};
+
+/// Scrubs every element with `T::zeroize` and leaves the vec empty.
+/// On the stack, the whole inline buffer is then overwritten with zero bytes,
+/// including the unused slots; on the heap, the spare capacity is zeroed as well.
+#[cfg(feature = "zeroize")]
+impl<T: zeroize::Zeroize, const N: usize> zeroize::Zeroize for LocalStorageVec<T, N> {
+    fn zeroize(&mut self) {
+        match self {
+            Self::Stack { .. } => {
+                self.as_mut().iter_mut().for_each(T::zeroize);
+                self.clear();
+                if let Self::Stack { buf, .. } = self {
+                    buf.zeroize();
+                }
+            }
+            Self::Heap(v) => v.zeroize(),
+        }
+    }
+}
+
+/// A [LocalStorageVec] for secrets, which scrubs its whole inline buffer
+/// or heap allocation when dropped.
+///
+/// It only hands out mutable access that cannot move the elements behind its back:
+/// with the `zeroize` feature, spilling to the heap scrubs the inline buffer left behind,
+/// and [SecretLocalStorageVec::push] scrubs the old heap allocation whenever it has to
+/// grow it. Memory the vec occupied before
+/// being wrapped, and values taken out with [SecretLocalStorageVec::pop], are not covered.
+#[cfg(feature = "zeroize")]
+pub struct SecretLocalStorageVec<T: zeroize::Zeroize, const N: usize>(LocalStorageVec<T, N>);
+
+#[cfg(feature = "zeroize")]
+impl<T: zeroize::Zeroize, const N: usize> SecretLocalStorageVec<T, N> {
+    /// Appends `item`. If that needs more room, the elements are moved to a larger
+    /// heap allocation and the storage they leave behind is scrubbed before it is freed.
+    pub fn push(&mut self, item: T) {
+        if let LocalStorageVec::Heap(v) = &mut self.0 {
+            if v.len() == v.capacity() {
+                let mut grown = Vec::with_capacity(2 * v.len());
+                grown.append(v);
+                // Zeroes the now unused capacity before the allocation is freed
+                zeroize::Zeroize::zeroize(v);
+                *v = grown;
+            }
+        }
+        // A full stack spills, which scrubs the inline buffer
+        self.0.push(item);
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.0.pop()
+    }
+
+    /// The elements, for changing them in place
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        self.0.as_mut()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<T: zeroize::Zeroize, const N: usize> From<LocalStorageVec<T, N>> for SecretLocalStorageVec<T, N> {
+    fn from(vec: LocalStorageVec<T, N>) -> Self {
+        Self(vec)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<T: zeroize::Zeroize, const N: usize> Deref for SecretLocalStorageVec<T, N> {
+    type Target = LocalStorageVec<T, N>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<T: zeroize::Zeroize, const N: usize> Drop for SecretLocalStorageVec<T, N> {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.0);
+    }
+}
+
+/// Serializes as a plain sequence of the elements
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize, const N: usize> serde::Serialize for LocalStorageVec<T, N> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+/// Deserializes a sequence onto the stack if it fits, and onto the heap otherwise
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>, const N: usize> serde::Deserialize<'de> for LocalStorageVec<T, N> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct SeqVisitor<T, const N: usize>(std::marker::PhantomData<T>);
+
+        impl<'d, T: serde::Deserialize<'d>, const N: usize> serde::de::Visitor<'d> for SeqVisitor<T, N> {
+            type Value = LocalStorageVec<T, N>;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(formatter, "a sequence")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'d>,
+            {
+                let mut vec = LocalStorageVec::new();
+                if let Some(len) = seq.size_hint() {
+                    // The hint comes from the input, so don't trust it with a huge allocation
+                    vec.reserve_exact(len.min(4096));
+                }
+                while let Some(item) = seq.next_element()? {
+                    vec.push(item);
+                }
+                Ok(vec)
+            }
+        }
+
+        deserializer.deserialize_seq(SeqVisitor(std::marker::PhantomData))
+    }
+}
+
+/// Moves the elements out of a [LocalStorageVec], front to back.
+/// Elements that were not yielded are dropped along with the iterator.
+pub struct LocalStorageVecIter<T, const N: usize> {
+    storage: IterStorage<T, N>,
+}
+
+enum IterStorage<T, const N: usize> {
+    /// The slots in `alive` are initialized and not yet yielded
+    Stack {
+        buf: [MaybeUninit<T>; N],
+        alive: Range<usize>,
+    },
+    Heap(std::vec::IntoIter<T>),
+}
+
+impl<T, const N: usize> Iterator for LocalStorageVecIter<T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.storage {
+            IterStorage::Stack { buf, alive } => {
+                let i = alive.next()?;
+                // SAFETY: slot `i` was alive and is never read again
+                Some(unsafe { buf[i].assume_init_read() })
+            }
+            IterStorage::Heap(it) => it.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.storage {
+            IterStorage::Stack { alive, .. } => alive.size_hint(),
+            IterStorage::Heap(it) => it.size_hint(),
+        }
+    }
+}
+
+impl<T, const N: usize> DoubleEndedIterator for LocalStorageVecIter<T, N> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match &mut self.storage {
+            IterStorage::Stack { buf, alive } => {
+                let i = alive.next_back()?;
+                // SAFETY: slot `i` was alive and is never read again
+                Some(unsafe { buf[i].assume_init_read() })
+            }
+            IterStorage::Heap(it) => it.next_back(),
+        }
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for LocalStorageVecIter<T, N> {}
+
+impl<T, const N: usize> Drop for LocalStorageVecIter<T, N> {
+    fn drop(&mut self) {
+        if let IterStorage::Stack { buf, alive } = &mut self.storage {
+            // SAFETY: the alive slots are initialized and were not yielded
+            unsafe { ptr::drop_in_place(init_slice_mut(&mut buf[alive.clone()])) };
+        }
+    }
+}
+use std::ops::IndexMut;
+
+impl<T, const N: usize> IndexMut<usize> for LocalStorageVec<T, N> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        match self {
+            LocalStorageVec::Stack { len, .. } if index < *len => &mut self.as_mut()[index],
+            LocalStorageVec::Heap(v) => &mut v[index],
+            _ => panic!("Index out of bounds"),
+        }
+    }
+}
+impl<T, const N: usize> IntoIterator for LocalStorageVec<T, N> {
+    type Item = T;
+    type IntoIter = LocalStorageVecIter<T, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let storage = match self.into_parts() {
+            Ok(v) => IterStorage::Heap(v.into_iter()),
+            Err((buf, len)) => IterStorage::Stack { buf, alive: 0..len },
+        };
+        LocalStorageVecIter { storage }
+    }
+}
+
+/// Collects onto the stack, spilling to the heap only once
+/// the items no longer fit there.
+impl<T, const N: usize> FromIterator<T> for LocalStorageVec<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = Self::new();
+        vec.extend(iter);
+        vec
+    }
+}
+
+impl<T, const N: usize> Extend<T> for LocalStorageVec<T, N> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut iter = iter.into_iter();
+        while let Self::Stack { .. } = self {
+            match iter.next() {
+                Some(item) => self.push(item),
+                None => return,
+            }
+        }
+        // Let `Vec` reserve for the rest of the iterator up front
+        if let Self::Heap(v) = self {
+            v.extend(iter);
+        }
+    }
+
+    #[cfg(feature = "nightly")]
+    fn extend_one(&mut self, item: T) {
+        self.push(item);
+    }
+
+    /// Spills to the heap at once if `additional` elements won't fit on the stack,
+    /// rather than on the push that overflows it.
+    #[cfg(feature = "nightly")]
+    fn extend_reserve(&mut self, additional: usize) {
+        match self {
+            Self::Heap(v) => v.reserve(additional),
+            Self::Stack { .. } => self.reserve_exact(additional),
+        }
+    }
+}
+
+use std::ops::{Index, RangeFrom, RangeInclusive, RangeTo, RangeToInclusive};
+
+impl<T, const N: usize> Index<usize> for LocalStorageVec<T, N> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        match self {
+            Self::Stack { len, .. } if index < *len => &self.as_ref()[index],
+            Self::Heap(v) => &v[index],
+            _ => panic!("Index out of bounds"),
+        }
+    }
+}
+
+impl<T, const N: usize> Index<RangeTo<usize>> for LocalStorageVec<T, N> {
+    type Output = [T];
+
+    fn index(&self, index: RangeTo<usize>) -> &Self::Output {
+        match self {
+            Self::Stack { len, .. } if index.end <= *len => &self.as_ref()[index],
+            Self::Heap(v) => &v[..index.end],
+            _ => panic!("Index out of bounds"),
+        }
+    }
+}
+
+impl<T, const N: usize> Index<RangeFrom<usize>> for LocalStorageVec<T, N> {
+    type Output = [T];
+
+    fn index(&self, index: RangeFrom<usize>) -> &Self::Output {
+        match self {
+            Self::Stack { len, .. } if index.start < *len => &self.as_ref()[index],
+            Self::Heap(v) => &v[index.start..],
+            _ => panic!("Index out of bounds"),
+        }
+    }
+}
+
+impl<T, const N: usize> Index<Range<usize>> for LocalStorageVec<T, N> {
+    type Output = [T];
+
+    fn index(&self, index: Range<usize>) -> &Self::Output {
+        match self {
+            Self::Stack { len, .. } if index.end <= *len => &self.as_ref()[index],
+            Self::Heap(v) => &v[index],
+            _ => panic!("Index out of bounds"),
+        }
+    }
+}
+
+impl<T, const N: usize> Index<RangeInclusive<usize>> for LocalStorageVec<T, N> {
+    type Output = [T];
+
+    fn index(&self, index: RangeInclusive<usize>) -> &Self::Output {
+        match self {
+            Self::Stack { len, .. } if *index.end() < *len => &self.as_ref()[index],
+            Self::Heap(v) => &v[index],
+            _ => panic!("Index out of bounds"),
+        }
+    }
+}
+
+impl<T, const N: usize> Index<RangeToInclusive<usize>> for LocalStorageVec<T, N> {
+    type Output = [T];
+
+    fn index(&self, index: RangeToInclusive<usize>) -> &Self::Output {
+        match self {
+            Self::Stack { len, .. } if index.end < *len => &self.as_ref()[index],
+            Self::Heap(v) => &v[index],
+            _ => panic!("Index out of bounds"),
+        }
+    }
+}
+
+use std::ops::{Deref, DerefMut};
+
+impl<T, const N: usize> Deref for LocalStorageVec<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        <Self as AsRef<[T]>>::as_ref(self)
+    }
+}
+use std::convert::AsRef;
+
+impl<T, const N: usize> AsRef<[T]> for LocalStorageVec<T, N> {
+    fn as_ref(&self) -> &[T] {
+        match self {
+            // SAFETY: the first `len` slots are initialized
+            LocalStorageVec::Stack { buf, len } => unsafe { init_slice(&buf[..*len]) },
+            LocalStorageVec::Heap(v) => v.as_ref(),
+        }
+    }
+}
+use std::convert::AsMut;
+
+impl<T, const N: usize> AsMut<[T]> for LocalStorageVec<T, N> {
+    fn as_mut(&mut self) -> &mut [T] {
+        match self {
+            // SAFETY: the first `len` slots are initialized
+            LocalStorageVec::Stack { buf, len } => unsafe { init_slice_mut(&mut buf[..*len]) },
+            LocalStorageVec::Heap(v) => v.as_mut(),
+        }
+    }
+}
+impl<T, const N: usize> DerefMut for LocalStorageVec<T, N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        <Self as AsMut<[T]>>::as_mut(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::LocalStorageVec;
+    use proptest::prelude::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+
+
+    #[test]
+    fn it_from_vecs() {
+        let vec: LocalStorageVec<usize, 10> = LocalStorageVec::from(vec![1, 2, 3]);
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+
+        let vec: LocalStorageVec<usize, 2> = LocalStorageVec::from(vec![1, 2, 3]);
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+    }
+
+
+    #[test]
+    fn it_as_refs() {
+        let vec: LocalStorageVec<i32, 256> = LocalStorageVec::from([0; 128]);
+        let slice: &[i32] = vec.as_ref();
+        assert!(slice.len() == 128);
+        let vec: LocalStorageVec<i32, 32> = LocalStorageVec::from([0; 128]);
+        let slice: &[i32] = vec.as_ref();
+        assert!(slice.len() == 128);
+
+        let mut vec: LocalStorageVec<i32, 256> = LocalStorageVec::from([0; 128]);
+        let slice_mut: &[i32] = vec.as_mut();
+        assert!(slice_mut.len() == 128);
+        let mut vec: LocalStorageVec<i32, 32> = LocalStorageVec::from([0; 128]);
+        let slice_mut: &[i32] = vec.as_mut();
+        assert!(slice_mut.len() == 128);
+    }
+
+
+    #[test]
+    fn it_constructs() {
+        let vec: LocalStorageVec<usize, 10> = LocalStorageVec::new();
+        assert!(matches!(vec, LocalStorageVec::Stack { buf: _, len: 0 }));
+    }
+
+
+    #[test]
+    fn it_lens() {
+        let vec: LocalStorageVec<_, 3> = LocalStorageVec::from([0, 1, 2]);
+        assert_eq!(vec.len(), 3);
+        let vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2]);
+        assert_eq!(vec.len(), 3);
+    }
+
+
+    #[test]
+    fn it_pushes() {
+        let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::new();
+        for value in 0..128 {
+            vec.push(value);
+        }
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 128, .. }));
+        for value in 128..256 {
+            vec.push(value);
+        }
+        assert!(matches!(&vec, LocalStorageVec::Heap(v) if v.len() == 256))
+    }
+
+
+    #[test]
+    fn it_spills_immediately_without_stack_capacity() {
+        let mut vec: LocalStorageVec<_, 0> = LocalStorageVec::new();
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 0, .. }));
+        assert_eq!(vec.capacity_remaining(), Some(0));
+        vec.push(1);
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert_eq!(vec.as_ref(), &[1]);
+
+        let vec: LocalStorageVec<_, 0> = LocalStorageVec::from([1, 2]);
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+    }
+
+
+    #[test]
+    fn it_spills_once() {
+        let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::new();
+        for value in 0..129 {
+            vec.push(value);
+        }
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        // Pushing up to twice the inline capacity must not move the heap buffer again
+        let ptr = vec.as_ptr();
+        for value in 129..256 {
+            vec.push(value);
+        }
+        assert_eq!(vec.as_ptr(), ptr);
+        assert!(vec.iter().copied().eq(0..256));
+    }
+
+
+    #[test]
+    fn it_grows_by_powers_of_two() {
+        let mut vec: LocalStorageVec<_, 5> = LocalStorageVec::from([0, 1, 2]);
+        vec.reserve(2);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+        vec.reserve(10);
+        assert!(matches!(&vec, LocalStorageVec::Heap(v) if v.capacity() == 16));
+
+        let mut vec: LocalStorageVec<_, 5> = LocalStorageVec::new();
+        let mut capacities = Vec::new();
+        for value in 0..1000 {
+            vec.push(value);
+            if let LocalStorageVec::Heap(v) = &vec {
+                if capacities.last() != Some(&v.capacity()) {
+                    capacities.push(v.capacity());
+                }
+            }
+        }
+        assert_eq!(capacities[0], LocalStorageVec::<i32, 5>::grown_capacity(6));
+        assert_eq!(capacities[0], 8);
+        assert!(capacities.windows(2).all(|pair| pair[0] < pair[1]));
+        // Amortized growth reallocates a logarithmic number of times
+        assert!(capacities.len() <= 8, "{capacities:?}");
+        assert!(vec.iter().copied().eq(0..1000));
+    }
+
+
+    #[test]
+    fn it_reserves_exact() {
+        let mut vec: LocalStorageVec<_, 8> = LocalStorageVec::from([0, 1, 2]);
+        vec.reserve_exact(5);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+
+        vec.reserve_exact(10);
+        assert!(matches!(&vec, LocalStorageVec::Heap(v) if v.capacity() == 13));
+        assert_eq!(vec.as_ref(), &[0, 1, 2]);
+
+        vec.reserve_exact(20);
+        assert!(matches!(&vec, LocalStorageVec::Heap(v) if v.capacity() >= 23));
+    }
+
+
+    #[test]
+    fn it_inserts_unique() {
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::new();
+        assert!(vec.insert_unique(1));
+        assert!(vec.insert_unique(2));
+        assert!(!vec.insert_unique(1));
+        assert!(vec.insert_unique(3));
+        assert!(!vec.insert_unique(3));
+        assert!(!vec.insert_unique(2));
+        assert_eq!(vec.as_ref(), &[1, 2, 3]);
+    }
+
+
+    #[test]
+    fn it_reports_remaining_capacity() {
+        let mut vec: LocalStorageVec<_, 3> = LocalStorageVec::new();
+        assert_eq!(vec.capacity_remaining(), Some(3));
+        vec.push(0);
+        assert_eq!(vec.capacity_remaining(), Some(2));
+        vec.push(1);
+        vec.push(2);
+        assert_eq!(vec.capacity_remaining(), Some(0));
+        vec.push(3);
+        assert_eq!(vec.capacity_remaining(), None);
+    }
+
+
+    #[test]
+    fn it_pops() {
+        let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 128]);
+        for _ in 0..128 {
+            assert_eq!(vec.pop(), Some(0))
+        }
+        assert_eq!(vec.pop(), None);
+
+        let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 256]);
+        for _ in 0..256 {
+            assert_eq!(vec.pop(), Some(0))
+        }
+        assert_eq!(vec.pop(), None);
+
+        let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::from(vec![0; 256]);
+        for _ in 0..256 {
+            assert_eq!(vec.pop(), Some(0))
         }
         assert_eq!(vec.pop(), None);
     }
 
 
     #[test]
-    fn it_inserts() {
-        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2]);
-        vec.insert(1, 3);
-        assert!(matches!(
-            vec,
-            LocalStorageVec::Stack {
-                buf: [0, 3, 1, 2],
-                len: 4
-            }
-        ));
+    fn it_inserts() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2]);
+        vec.insert(1, 3);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 4, .. }));
+        assert_eq!(vec.as_ref(), &[0, 3, 1, 2]);
+
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2, 3]);
+        vec.insert(1, 3);
+        assert!(matches!(vec, LocalStorageVec::Heap { .. }));
+        assert_eq!(vec.as_ref(), &[0, 3, 1, 2, 3]);
+
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2, 3, 4]);
+        vec.insert(1, 3);
+        assert!(matches!(vec, LocalStorageVec::Heap { .. }));
+        assert_eq!(vec.as_ref(), &[0, 3, 1, 2, 3, 4])
+    }
+
+
+    #[test]
+    fn it_inserts_slices() {
+        let mut vec: LocalStorageVec<_, 8> = LocalStorageVec::from([0, 1, 2]);
+        vec.insert_slice(1, &[7, 8, 9]);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 6, .. }));
+        assert_eq!(vec.as_ref(), &[0, 7, 8, 9, 1, 2]);
+
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2]);
+        vec.insert_slice(1, &[7, 8, 9]);
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert_eq!(vec.as_ref(), &[0, 7, 8, 9, 1, 2]);
+
+        vec.insert_slice(6, &[3, 4]);
+        assert_eq!(vec.as_ref(), &[0, 7, 8, 9, 1, 2, 3, 4]);
+    }
+
+
+    #[test]
+    fn it_removes() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2]);
+        let elem = vec.remove(1);
+
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 2, .. }));
+        assert_eq!(vec.as_ref(), &[0, 2]);
+        assert_eq!(elem, 1);
+
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2]);
+        let elem = vec.remove(1);
+        assert!(matches!(vec, LocalStorageVec::Heap(..)));
+        assert_eq!(vec.as_ref(), &[0, 2]);
+        assert_eq!(elem, 1);
+    }
+
+
+    #[test]
+    fn it_removes_all() {
+        let mut vec: LocalStorageVec<_, 8> = LocalStorageVec::from([0, 1, 2, 3, 4, 5, 6]);
+        vec.remove_all(&[5, 1, 3, 1]);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 4, .. }));
+        assert_eq!(vec.as_ref(), &[0, 2, 4, 6]);
+
+        vec.remove_all(&[]);
+        assert_eq!(vec.as_ref(), &[0, 2, 4, 6]);
+
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2, 3, 4, 5, 6]);
+        vec.remove_all(&[0, 6]);
+        assert_eq!(vec.as_ref(), &[1, 2, 3, 4, 5]);
+    }
+
+
+    #[test]
+    fn it_replaces() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([String::from("a"), "b".into()]);
+        assert_eq!(vec.replace(1, "c".into()), "b");
+        assert_eq!(vec.as_ref(), ["a", "c"]);
+
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2, 3]);
+        assert_eq!(vec.replace(3, 30), 3);
+        assert_eq!(vec.as_ref(), &[0, 1, 2, 30]);
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn it_panics_on_replace_past_len() {
+        let mut vec: LocalStorageVec<_, 8> = LocalStorageVec::from([0, 1, 2]);
+        vec.replace(3, 3);
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn it_panics_on_remove_all_past_len() {
+        let mut vec: LocalStorageVec<_, 8> = LocalStorageVec::from([0, 1, 2]);
+        vec.remove_all(&[1, 3]);
+    }
+
+
+    #[test]
+    fn it_clears() {
+        let mut vec: LocalStorageVec<_, 10> = LocalStorageVec::from([0, 1, 2, 3]);
+        assert!(matches!(vec, LocalStorageVec::Stack { buf: _, len: 4 }));
+        vec.clear();
+        assert_eq!(vec.len(), 0);
+
+        let mut vec: LocalStorageVec<_, 3> = LocalStorageVec::from([0, 1, 2, 3]);
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        vec.clear();
+        assert_eq!(vec.len(), 0);
+    }
+
+
+    #[test]
+    fn it_splits_to() {
+        let mut vec: LocalStorageVec<_, 8> = LocalStorageVec::from([0, 1, 2, 3, 4]);
+        let front = vec.split_to(2);
+        assert!(matches!(front, LocalStorageVec::Stack { len: 2, .. }));
+        assert_eq!(front.as_ref(), &[0, 1]);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+        assert_eq!(vec.as_ref(), &[2, 3, 4]);
+
+        let front = vec.split_to(3);
+        assert_eq!(front.as_ref(), &[2, 3, 4]);
+        assert_eq!(vec.len(), 0);
+
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2, 3, 4]);
+        let front = vec.split_to(3);
+        assert_eq!(front.as_ref(), &[0, 1, 2]);
+        assert_eq!(vec.as_ref(), &[3, 4]);
+    }
+
+
+    #[test]
+    fn it_splits_off() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from(vec![0, 1, 2, 3, 4, 5]);
+        let back = vec.split_off(3);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+        assert_eq!(vec.as_ref(), &[0, 1, 2]);
+        assert!(matches!(back, LocalStorageVec::Stack { len: 3, .. }));
+        assert_eq!(back.as_ref(), &[3, 4, 5]);
+
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from(vec![0, 1, 2, 3, 4, 5]);
+        let back = vec.split_off(1);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 1, .. }));
+        assert!(matches!(back, LocalStorageVec::Heap(_)));
+        assert_eq!(back.as_ref(), &[1, 2, 3, 4, 5]);
+
+        let mut vec: LocalStorageVec<_, 8> = LocalStorageVec::from([0, 1, 2, 3, 4]);
+        let back = vec.split_off(2);
+        assert_eq!(vec.as_ref(), &[0, 1]);
+        assert!(matches!(back, LocalStorageVec::Stack { len: 3, .. }));
+        assert_eq!(back.as_ref(), &[2, 3, 4]);
+    }
+
+
+    #[test]
+    fn it_splits_into_chunks() {
+        let vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2, 3, 4, 5, 6]);
+        let chunks: Vec<_> = vec.into_chunks(3).collect();
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|chunk| matches!(chunk, LocalStorageVec::Stack { .. })));
+        assert_eq!(chunks[0].as_ref(), &[0, 1, 2]);
+        assert_eq!(chunks[1].as_ref(), &[3, 4, 5]);
+        assert_eq!(chunks[2].as_ref(), &[6]);
+
+        let vec: LocalStorageVec<_, 2> = LocalStorageVec::from(["a", "b", "c", "d", "e"].map(String::from));
+        let chunks: Vec<_> = vec.into_chunks(3).collect();
+        assert!(matches!(chunks[0], LocalStorageVec::Heap(_)));
+        assert_eq!(chunks[0].as_ref(), ["a", "b", "c"]);
+        assert!(matches!(chunks[1], LocalStorageVec::Stack { len: 2, .. }));
+        assert_eq!(chunks[1].as_ref(), ["d", "e"]);
+
+        assert_eq!(LocalStorageVec::<i32, 2>::new().into_chunks(3).count(), 0);
+    }
+
+
+    #[test]
+    fn it_partitions() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2, 3, 4, 5, 6]);
+        let odds = vec.partition(|x| x % 2 == 0);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 4, .. }));
+        assert_eq!(vec.as_ref(), &[0, 2, 4, 6]);
+        assert!(matches!(odds, LocalStorageVec::Stack { len: 3, .. }));
+        assert_eq!(odds.as_ref(), &[1, 3, 5]);
+
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2, 4, 6]);
+        let odds = vec.partition(|x| x % 2 == 0);
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert_eq!(vec.as_ref(), &[0, 2, 4, 6]);
+        assert!(matches!(odds, LocalStorageVec::Stack { len: 1, .. }));
+        assert_eq!(odds.as_ref(), &[1]);
+    }
+
+
+    #[test]
+    fn it_iters() {
+        let vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 32]);
+        let mut iter = vec.into_iter();
+        for item in &mut iter {
+            assert_eq!(item, 0);
+        }
+        assert_eq!(iter.next(), None);
+
+        let vec: LocalStorageVec<_, 128> = LocalStorageVec::from(vec![0; 128]);
+        let mut iter = vec.into_iter();
+        for item in &mut iter {
+            assert_eq!(item, 0);
+        }
+        assert_eq!(iter.next(), None);
+    }
+
+
+    #[test]
+    fn it_collects_arrays() {
+        let vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2]);
+        assert_eq!(vec.collect_array::<3>().ok(), Some([0, 1, 2]));
+
+        let vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2]);
+        assert_eq!(vec.collect_array::<3>().ok(), Some([0, 1, 2]));
+
+        let vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2]);
+        let vec = vec.collect_array::<4>().unwrap_err();
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+        let vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2]);
+        let vec = vec.collect_array::<2>().unwrap_err();
+        assert_eq!(vec.as_ref(), &[0, 1, 2]);
+
+        let vec: LocalStorageVec<String, 4> = LocalStorageVec::from(["a".to_owned(), "b".to_owned()]);
+        let array: [String; 2] = vec.collect_array().ok().unwrap();
+        assert_eq!(array, ["a", "b"]);
+    }
+
+
+    #[test]
+    fn it_gets_many_mut() {
+        let mut vec: LocalStorageVec<_, 8> = LocalStorageVec::from([0, 1, 2, 3]);
+        let [a, b] = vec.get_many_mut([3, 1]).unwrap();
+        std::mem::swap(a, b);
+        assert_eq!(vec.as_ref(), &[0, 3, 2, 1]);
+
+        assert!(vec.get_many_mut([1, 2, 1]).is_none());
+        assert!(vec.get_many_mut([0, 4]).is_none());
+
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2, 3]);
+        let [a, b, c] = vec.get_many_mut([0, 2, 3]).unwrap();
+        *a += 10;
+        *b += 10;
+        *c += 10;
+        assert_eq!(vec.as_ref(), &[10, 1, 12, 13]);
+    }
+
+
+    #[test]
+    fn it_strides() {
+        let vec: LocalStorageVec<_, 8> = LocalStorageVec::from([0, 1, 2, 3, 4, 5, 6]);
+        assert!(vec.stride(2).eq(&[0, 2, 4, 6]));
+        assert!(vec.stride(3).eq(&[0, 3, 6]));
+        assert!(vec.stride(10).eq(&[0]));
+    }
+
+
+    #[test]
+    fn it_finds_positions() {
+        let vec: LocalStorageVec<_, 8> = LocalStorageVec::from([1, 2, 3, 2, 5, 2, 7]);
+        assert_eq!(vec.rposition(|&x| x == 7), Some(6));
+        assert_eq!(vec.rposition(|&x| x == 2), Some(5));
+        assert_eq!(vec.position(|&x| x == 2), Some(1));
+        assert_eq!(vec.rposition(|&x| x > 7), None);
+
+        let vec: LocalStorageVec<_, 2> = LocalStorageVec::from([1, 2, 3, 2, 5]);
+        assert_eq!(vec.rposition(|&x| x == 2), Some(3));
+        assert_eq!(LocalStorageVec::<i32, 2>::new().rposition(|_| true), None);
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn it_panics_on_zero_stride() {
+        let vec: LocalStorageVec<_, 8> = LocalStorageVec::from([0, 1, 2]);
+        let _ = vec.stride(0);
+    }
+
+
+    #[test]
+    fn it_round_trips_through_parts() {
+        let vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2]);
+        let parts = vec.into_parts();
+        assert!(matches!(parts, Err((_, 3))));
+        let vec = unsafe { LocalStorageVec::from_parts(parts) };
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+        assert_eq!(vec.as_ref(), &[0, 1, 2]);
+
+        let vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2]);
+        let parts = vec.into_parts();
+        let ptr = parts.as_ref().unwrap().as_ptr();
+        let vec = unsafe { LocalStorageVec::<_, 2>::from_parts(parts) };
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert_eq!(vec.as_ref(), &[0, 1, 2]);
+        assert_eq!(vec.as_ptr(), ptr);
+    }
+
+
+    #[test]
+    fn it_indexes() {
+        let vec: LocalStorageVec<i32, 10> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
+        assert_eq!(vec[1], 1);
+        assert_eq!(vec[..2], [0, 1]);
+        assert_eq!(vec[4..], [4, 5]);
+        assert_eq!(vec[1..3], [1, 2]);
+    }
+
+
+    #[test]
+    fn it_indexes_inclusive_ranges() {
+        let vec: LocalStorageVec<i32, 10> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
+        assert_eq!(vec[1..=3], [1, 2, 3]);
+        assert_eq!(vec[..=2], [0, 1, 2]);
+        assert_eq!(vec[..=5], [0, 1, 2, 3, 4, 5]);
+
+        let vec: LocalStorageVec<i32, 2> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
+        assert_eq!(vec[1..=3], [1, 2, 3]);
+        assert_eq!(vec[..=2], [0, 1, 2]);
+        assert_eq!(vec[..=5], [0, 1, 2, 3, 4, 5]);
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn it_panics_on_inclusive_range_past_len() {
+        let vec: LocalStorageVec<i32, 10> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
+        let _ = &vec[1..=6];
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn it_panics_on_inclusive_range_past_len_on_heap() {
+        let vec: LocalStorageVec<i32, 2> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
+        let _ = &vec[..=6];
+    }
+
+
+    #[test]
+    fn it_borrowing_iters() {
+        let vec: LocalStorageVec<String, 10> = LocalStorageVec::from([
+            "0".to_owned(),
+            "1".to_owned(),
+            "2".to_owned(),
+            "3".to_owned(),
+            "4".to_owned(),
+            "5".to_owned(),
+        ]);
+        let iter = vec.iter();
+        for _ in iter {}
+        drop(vec);
+    }
+
+
+    #[test]
+    fn it_derefs() {
+        use std::ops::{Deref, DerefMut};
+        let vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 128]);
+        let chunks = vec.chunks(4);
+        let slice: &[_] = vec.deref();
+
+        let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 128]);
+        let chunks = vec.chunks_mut(4);
+        let slice: &mut [_] = vec.deref_mut();
+    }
+
+
+    #[derive(Default)]
+    struct DropCounter {
+        id: usize,
+        dropped: Option<Rc<RefCell<Vec<usize>>>>,
+    }
+
+    impl DropCounter {
+        fn new(id: usize, dropped: &Rc<RefCell<Vec<usize>>>) -> Self {
+            Self {
+                id,
+                dropped: Some(Rc::clone(dropped)),
+            }
+        }
+    }
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            if let Some(dropped) = &self.dropped {
+                dropped.borrow_mut().push(self.id);
+            }
+        }
+    }
+
+
+    #[test]
+    fn it_filter_maps_in_place() {
+        let halve_evens = |x: i32| if x % 2 == 0 { Some(x / 2) } else { None };
+
+        let mut vec: LocalStorageVec<_, 8> = LocalStorageVec::from([2, 3, 4, 5, 8]);
+        vec.filter_map_in_place(halve_evens);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+        assert_eq!(vec.as_ref(), &[1, 2, 4]);
+
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from([2, 3, 4, 5, 8]);
+        vec.filter_map_in_place(halve_evens);
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert_eq!(vec.as_ref(), &[1, 2, 4]);
+    }
+
+
+    #[test]
+    fn it_truncates_while() {
+        let mut vec: LocalStorageVec<_, 8> = LocalStorageVec::from([1, 0, 2, 0, 0, 0]);
+        assert_eq!(vec.truncate_while(|&x| x == 0), 3);
+        assert_eq!(vec.as_ref(), &[1, 0, 2]);
+        assert_eq!(vec.truncate_while(|&x| x == 0), 0);
+        assert_eq!(vec.as_ref(), &[1, 0, 2]);
+
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 0, 0]);
+        assert_eq!(vec.truncate_while(|&x| x == 0), 3);
+        assert_eq!(vec.len(), 0);
+    }
+
+
+    #[test]
+    fn it_dedups() {
+        let mut vec: LocalStorageVec<_, 10> = LocalStorageVec::from([1, 1, 2, 3, 3, 3, 1]);
+        vec.dedup();
+        assert_eq!(vec.as_ref(), &[1, 2, 3, 1]);
+
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from([1, 1, 2, 3, 3, 3, 1]);
+        vec.dedup();
+        assert_eq!(vec.as_ref(), &[1, 2, 3, 1]);
+
+        let mut vec: LocalStorageVec<i32, 2> = LocalStorageVec::from([]);
+        vec.dedup();
+        assert_eq!(vec.len(), 0);
+    }
+
+
+    #[test]
+    fn it_dedups_after_append() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([1, 1, 2]);
+        let mut other: LocalStorageVec<_, 4> = LocalStorageVec::from(vec![2, 2, 3, 3, 3, 1]);
+        assert!(matches!(vec, LocalStorageVec::Stack { .. }));
+        assert!(matches!(other, LocalStorageVec::Heap(_)));
+
+        vec.append(&mut other);
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert_eq!(vec.as_ref(), &[1, 1, 2, 2, 2, 3, 3, 3, 1]);
+        assert_eq!(other.len(), 0);
+
+        vec.dedup();
+        assert_eq!(vec.as_ref(), &[1, 2, 3, 1]);
+    }
+
+
+    #[test]
+    fn it_extends_from_lsv() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1]);
+        let other: LocalStorageVec<_, 8> = LocalStorageVec::from([2, 3]);
+        vec.extend_from_lsv(other);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 4, .. }));
+        assert_eq!(vec.as_ref(), &[0, 1, 2, 3]);
+
+        let other: LocalStorageVec<_, 8> = LocalStorageVec::from([4, 5, 6, 7, 8]);
+        vec.extend_from_lsv(other);
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert_eq!(vec.as_ref(), &[0, 1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+
+    #[test]
+    fn it_moves_into() {
+        let mut target: LocalStorageVec<_, 8> = LocalStorageVec::from([0, 1, 2]);
+        LocalStorageVec::from([3, 4]).move_into(&mut target);
+        assert!(matches!(target, LocalStorageVec::Stack { len: 5, .. }));
+        assert_eq!(target.as_ref(), &[0, 1, 2, 3, 4]);
+
+        LocalStorageVec::from([5, 6, 7, 8]).move_into(&mut target);
+        assert!(matches!(target, LocalStorageVec::Heap(_)));
+        assert_eq!(target.as_ref(), &[0, 1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let mut target: LocalStorageVec<_, 2> = LocalStorageVec::new();
+        let heap: LocalStorageVec<_, 2> = LocalStorageVec::from(vec![String::from("a"), String::from("b"), "c".into()]);
+        let ptr = heap.as_ptr();
+        heap.move_into(&mut target);
+        assert_eq!(target.as_ptr(), ptr);
+        assert_eq!(target.as_ref(), ["a", "b", "c"]);
+    }
+
+
+    #[test]
+    fn it_dedups_and_retains_without_default() {
+        #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+        struct NoDefault(u8);
+
+        let mut vec: LocalStorageVec<_, 8> = LocalStorageVec::from([3, 1, 1, 2, 3, 4].map(NoDefault));
+        vec.retain(|x| x.0 != 4);
+        vec.dedup();
+        assert_eq!(vec.as_ref(), [3, 1, 2, 3].map(NoDefault));
+        assert_eq!(vec.truncate_while(|x| x.0 > 2), 1);
+        vec.sort_dedup();
+        assert_eq!(vec.as_ref(), [1, 2, 3].map(NoDefault));
+    }
 
-        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2, 3]);
-        vec.insert(1, 3);
-        assert!(matches!(vec, LocalStorageVec::Heap { .. }));
-        assert_eq!(vec.as_ref(), &[0, 3, 1, 2, 3]);
 
-        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2, 3, 4]);
-        vec.insert(1, 3);
-        assert!(matches!(vec, LocalStorageVec::Heap { .. }));
-        assert_eq!(vec.as_ref(), &[0, 3, 1, 2, 3, 4])
+    #[test]
+    fn it_sort_dedups() {
+        let mut vec: LocalStorageVec<_, 10> = LocalStorageVec::from([3, 1, 2, 3, 1, 5, 2]);
+        vec.sort_dedup();
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 4, .. }));
+        assert_eq!(vec.as_ref(), &[1, 2, 3, 5]);
+
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from([3, 1, 2, 3, 1, 5, 2]);
+        vec.sort_dedup();
+        assert_eq!(vec.as_ref(), &[1, 2, 3, 5]);
     }
 
 
     #[test]
-    fn it_removes() {
-        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2]);
-        let elem = vec.remove(1);
+    fn it_fills_with_default() {
+        let mut vec: LocalStorageVec<_, 8> = LocalStorageVec::from([1, 2, 3]);
+        vec.fill_with_default();
+        assert_eq!(vec.len(), 3);
+        assert_eq!(vec.as_ref(), &[0, 0, 0]);
+
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from(["a".to_owned(), "b".to_owned(), "c".to_owned()]);
+        vec.fill_with_default();
+        assert_eq!(vec.len(), 3);
+        assert!(vec.iter().all(String::is_empty));
+    }
+
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn it_zeroizes() {
+        use zeroize::Zeroize;
+
+        let mut vec: LocalStorageVec<u8, 8> = LocalStorageVec::from([1, 2, 3, 4, 5]);
+        vec.pop();
+        vec.pop();
+        vec.zeroize();
+        assert!(matches!(&vec, LocalStorageVec::Stack { buf, len: 0 }
+            if buf.iter().all(|b| unsafe { b.assume_init() } == 0)));
+
+        let mut vec: LocalStorageVec<u8, 2> = LocalStorageVec::from([1, 2, 3, 4, 5]);
+        vec.zeroize();
+        assert_eq!(vec.len(), 0);
+    }
+
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn it_scrubs_secrets_on_drop() {
+        use crate::SecretLocalStorageVec;
+        use std::cell::Cell;
+
+        thread_local! {
+            static SCRUBBED: Cell<usize> = const { Cell::new(0) };
+        }
 
-        assert!(matches!(
-            vec,
-            LocalStorageVec::Stack {
-                buf: [0, 2, _, _],
-                len: 2
+        struct Spy(u8);
+
+        impl zeroize::Zeroize for Spy {
+            fn zeroize(&mut self) {
+                self.0 = 0;
+                SCRUBBED.with(|scrubbed| scrubbed.set(scrubbed.get() + 1));
             }
-        ));
-        assert_eq!(elem, 1);
+        }
 
-        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2]);
-        let elem = vec.remove(1);
-        assert!(matches!(vec, LocalStorageVec::Heap(..)));
-        assert_eq!(vec.as_ref(), &[0, 2]);
-        assert_eq!(elem, 1);
+        let vec: LocalStorageVec<Spy, 4> = LocalStorageVec::from([Spy(1), Spy(2)]);
+        let secret = SecretLocalStorageVec::from(vec);
+        assert_eq!(secret.len(), 2);
+        drop(secret);
+        // Only the initialized elements are scrubbed with `T::zeroize`
+        assert_eq!(SCRUBBED.with(Cell::get), 2);
+
+        let vec: LocalStorageVec<Spy, 1> = LocalStorageVec::from([Spy(1), Spy(2), Spy(3)]);
+        drop(SecretLocalStorageVec::from(vec));
+        assert_eq!(SCRUBBED.with(Cell::get), 5);
+
+        // Growing scrubs the storage that is left behind
+        #[cfg(feature = "metrics")]
+        let spills = crate::spill_count();
+        let mut secret = SecretLocalStorageVec::from(LocalStorageVec::<Spy, 2>::new());
+        secret.push(Spy(1));
+        secret.push(Spy(2));
+        secret.push(Spy(3));
+        assert!(matches!(*secret, LocalStorageVec::Heap(_)));
+        #[cfg(feature = "metrics")]
+        assert!(crate::spill_count() > spills);
+        secret.as_mut_slice()[0].0 = 4;
+        assert!(secret.iter().map(|spy| spy.0).eq([4, 2, 3]));
+        assert_eq!(secret.pop().map(|spy| spy.0), Some(3));
+        drop(secret);
+        assert_eq!(SCRUBBED.with(Cell::get), 7);
     }
 
 
     #[test]
-    fn it_clears() {
-        let mut vec: LocalStorageVec<_, 10> = LocalStorageVec::from([0, 1, 2, 3]);
-        assert!(matches!(vec, LocalStorageVec::Stack { buf: _, len: 4 }));
-        vec.clear();
-        assert_eq!(vec.len(), 0);
+    fn it_finds_subslices() {
+        let vec: LocalStorageVec<_, 10> = LocalStorageVec::from([0, 1, 2, 3, 1, 2]);
+        assert_eq!(vec.find_subslice(&[1, 2]), Some(1));
+        assert_eq!(vec.find_subslice(&[2, 3, 1]), Some(2));
+        assert_eq!(vec.find_subslice(&[3, 2]), None);
+        assert_eq!(vec.find_subslice(&[]), Some(0));
+
+        let vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2, 3, 1, 2]);
+        assert_eq!(vec.find_subslice(&[3, 1, 2]), Some(3));
+        assert_eq!(vec.find_subslice(&[0, 1, 2, 3, 1, 2, 3]), None);
+    }
 
-        let mut vec: LocalStorageVec<_, 3> = LocalStorageVec::from([0, 1, 2, 3]);
+
+    #[test]
+    fn it_clones_into_vec() {
+        let mut target = Vec::with_capacity(16);
+        target.push(42);
+        let ptr = target.as_ptr();
+
+        let vec: LocalStorageVec<_, 10> = LocalStorageVec::from([0, 1, 2, 3]);
+        vec.clone_into_vec(&mut target);
+        assert_eq!(target, [0, 1, 2, 3]);
+
+        let vec: LocalStorageVec<_, 2> = LocalStorageVec::from([4, 5, 6]);
+        vec.clone_into_vec(&mut target);
+        assert_eq!(target, [4, 5, 6]);
+
+        assert_eq!(target.capacity(), 16);
+        assert_eq!(target.as_ptr(), ptr);
+    }
+
+
+    #[test]
+    fn it_run_length_encodes() {
+        let vec: LocalStorageVec<_, 10> = LocalStorageVec::from([1, 1, 1, 2, 3, 3, 1]);
+        assert_eq!(vec.run_length_encode(), [(1, 3), (2, 1), (3, 2), (1, 1)]);
+
+        let vec: LocalStorageVec<_, 2> = LocalStorageVec::from(['a', 'a', 'b', 'b', 'b']);
+        assert_eq!(vec.run_length_encode(), [('a', 2), ('b', 3)]);
+
+        let vec: LocalStorageVec<i32, 2> = LocalStorageVec::from([]);
+        assert!(vec.run_length_encode().is_empty());
+    }
+
+
+    #[test]
+    fn it_retains() {
+        let mut vec: LocalStorageVec<_, 10> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
+        vec.retain(|x| x % 2 == 0);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+        assert_eq!(vec.as_ref(), &[0, 2, 4]);
+
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
+        vec.retain(|x| x % 2 == 0);
+        assert_eq!(vec.as_ref(), &[0, 2, 4]);
+    }
+
+
+    #[test]
+    fn it_retains_without_copy() {
+        let dropped = Rc::new(RefCell::new(Vec::new()));
+        let mut vec: LocalStorageVec<DropCounter, 8> =
+            LocalStorageVec::from([0, 1, 2, 3, 4, 5].map(|id| DropCounter::new(id, &dropped)));
+        vec.retain(|item| item.id % 2 == 0);
+
+        let mut removed = dropped.borrow().clone();
+        removed.sort();
+        assert_eq!(removed, [1, 3, 5]);
+        assert_eq!(vec.iter().map(|item| item.id).collect::<Vec<_>>(), [0, 2, 4]);
+
+        drop(vec);
+        let mut all = dropped.borrow().clone();
+        all.sort();
+        assert_eq!(all, [0, 1, 2, 3, 4, 5]);
+    }
+
+
+    #[test]
+    fn it_extends_on_the_heap_with_a_single_reservation() {
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from(vec![0, 1, 2, 3]);
+        vec.extend(4..100);
+        assert!(matches!(&vec, LocalStorageVec::Heap(v) if v.capacity() == 100));
+        assert_eq!(vec.as_ref(), (0..100).collect::<Vec<_>>());
+
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::new();
+        vec.extend([0, 1, 2]);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+        vec.extend(3..6);
+        assert_eq!(vec.as_ref(), &[0, 1, 2, 3, 4, 5]);
+    }
+
+
+    #[test]
+    fn it_shrinks_back_onto_the_stack() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from(vec![1, 2, 3, 4, 5]);
+        vec.shrink_to_fit();
         assert!(matches!(vec, LocalStorageVec::Heap(_)));
-        vec.clear();
-        assert_eq!(vec.len(), 0);
+
+        vec.pop();
+        vec.pop();
+        vec.shrink_to_fit();
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+        assert_eq!(vec.as_ref(), &[1, 2, 3]);
+
+        vec.shrink_to_fit();
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+        assert_eq!(vec.as_ref(), &[1, 2, 3]);
     }
 
 
     #[test]
-    fn it_iters() {
-        let vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 32]);
-        let mut iter = vec.into_iter();
-        for item in &mut iter {
-            assert_eq!(item, 0);
+    fn it_pops_if() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([1, 2, 3]);
+        assert_eq!(vec.pop_if(|&x| x == 3), Some(3));
+        assert_eq!(vec.pop_if(|&x| x == 3), None);
+        assert_eq!(vec.as_ref(), &[1, 2]);
+
+        *vec.peek_last_mut().unwrap() = 5;
+        assert_eq!(vec.peek_last(), Some(&5));
+
+        let mut vec: LocalStorageVec<i32, 4> = LocalStorageVec::new();
+        assert_eq!(vec.pop_if(|_| true), None);
+        assert_eq!(vec.peek_last(), None);
+    }
+
+
+    #[test]
+    fn it_commits_writes_through_the_pointer() {
+        let mut vec: LocalStorageVec<u32, 4> = LocalStorageVec::new();
+        let ptr = vec.as_mut_ptr();
+        for i in 0..3 {
+            unsafe { ptr.add(i).write(i as u32 * 10) };
         }
-        assert_eq!(iter.next(), None);
+        unsafe { vec.set_len(3) };
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+        assert_eq!(vec.as_ref(), &[0, 10, 20]);
+
+        let mut vec: LocalStorageVec<u32, 4> = LocalStorageVec::from(Vec::with_capacity(8));
+        let ptr = vec.as_mut_ptr();
+        for i in 0..8 {
+            unsafe { ptr.add(i).write(i as u32) };
+        }
+        unsafe { vec.set_len(8) };
+        assert_eq!(vec.as_ref(), &[0, 1, 2, 3, 4, 5, 6, 7]);
+    }
 
-        let vec: LocalStorageVec<_, 128> = LocalStorageVec::from(vec![0; 128]);
+
+    #[test]
+    fn it_pushes_into_a_ring() {
+        let mut vec: LocalStorageVec<_, 3> = LocalStorageVec::new();
+        for i in 0..3 {
+            assert_eq!(vec.push_ring(i), None);
+        }
+        let evicted: Vec<_> = (3..7).map(|i| vec.push_ring(i).unwrap()).collect();
+        assert_eq!(evicted, [0, 1, 2, 3]);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+        assert_eq!(vec.as_ref(), &[4, 5, 6]);
+
+        let mut vec: LocalStorageVec<_, 0> = LocalStorageVec::new();
+        assert_eq!(vec.push_ring(1), Some(1));
+        assert!(vec.is_empty());
+    }
+
+
+    #[test]
+    fn it_mutates_each_with_its_index() {
+        let mut vec: LocalStorageVec<usize, 4> = LocalStorageVec::from([7; 3]);
+        vec.for_each_mut(|i, item| *item = i);
+        assert_eq!(vec.as_ref(), &[0, 1, 2]);
+
+        let mut vec: LocalStorageVec<usize, 2> = LocalStorageVec::from([7; 5]);
+        vec.for_each_mut(|i, item| *item += i);
+        assert_eq!(vec.as_ref(), &[7, 8, 9, 10, 11]);
+    }
+
+
+    #[test]
+    fn it_clears_and_drains() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([1, 2, 3]);
+        let drained: Vec<_> = vec.clear_and_drain().collect();
+        assert_eq!(drained, [1, 2, 3]);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 0, .. }));
+
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from(["a", "b", "c"].map(String::from).to_vec());
+        let drained: Vec<_> = vec.clear_and_drain().collect();
+        assert_eq!(drained, ["a", "b", "c"]);
+        assert!(vec.is_empty());
+    }
+
+
+    #[test]
+    fn it_drains_all() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([1, 2, 3]);
+        assert!(vec.drain_all().eq([1, 2, 3]));
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 0, .. }));
+
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from(["a", "b", "c"].map(String::from).to_vec());
+        let ptr = vec.as_ptr();
+        assert!(vec.drain_all().eq(["a", "b", "c"]));
+        assert!(matches!(&vec, LocalStorageVec::Heap(v) if v.is_empty() && v.capacity() >= 3));
+        vec.push("d".into());
+        assert_eq!(vec.as_ptr(), ptr);
+
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from(vec![1, 2, 3]);
+        assert_eq!(vec.drain_all().next(), Some(1));
+        assert!(vec.is_empty());
+    }
+
+
+    #[test]
+    fn it_finds_min_and_max() {
+        let vec: LocalStorageVec<i32, 4> = LocalStorageVec::new();
+        assert_eq!(vec.min(), None);
+        assert_eq!(vec.max(), None);
+        assert_eq!(vec.min_by_key(|x| x.abs()), None);
+        assert_eq!(vec.max_by_key(|x| x.abs()), None);
+
+        let vec: LocalStorageVec<i32, 4> = LocalStorageVec::from([3, -7, 1, 5, -2]);
+        assert_eq!(vec.min(), Some(&-7));
+        assert_eq!(vec.max(), Some(&5));
+        assert_eq!(vec.min_by_key(|x| x.abs()), Some(&1));
+        assert_eq!(vec.max_by_key(|x| x.abs()), Some(&-7));
+    }
+
+
+    #[test]
+    fn it_visits_mutable_windows() {
+        let mut vec: LocalStorageVec<f64, 4> = LocalStorageVec::from([0.0, 4.0, 8.0, 0.0]);
+        vec.for_each_window_mut(2, |window| window[0] = (window[0] + window[1]) / 2.0);
+        assert_eq!(vec.as_ref(), &[2.0, 6.0, 4.0, 0.0]);
+
+        let mut windows = 0;
+        vec.for_each_window_mut(5, |_| windows += 1);
+        assert_eq!(windows, 0);
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn it_panics_on_empty_windows() {
+        let mut vec: LocalStorageVec<i32, 4> = LocalStorageVec::from([1, 2]);
+        vec.for_each_window_mut(0, |_| {});
+    }
+
+
+    #[test]
+    fn it_inserts_and_removes_at_the_end() {
+        let mut vec: LocalStorageVec<i32, 8> = LocalStorageVec::from([1, 2, 3]);
+        vec.insert(3, 4);
+        assert_eq!(vec.as_ref(), &[1, 2, 3, 4]);
+        assert_eq!(vec.remove(3), 4);
+        assert_eq!(vec.as_ref(), &[1, 2, 3]);
+
+        let mut vec: LocalStorageVec<i32, 8> = LocalStorageVec::new();
+        vec.insert(0, 1);
+        assert_eq!(vec.as_ref(), &[1]);
+    }
+
+
+    #[test]
+    #[should_panic(expected = "insertion index (is 5) should be <= len (is 3)")]
+    fn it_panics_on_insert_past_len() {
+        let mut vec: LocalStorageVec<i32, 8> = LocalStorageVec::from([1, 2, 3]);
+        vec.insert(5, 9);
+    }
+
+
+    #[test]
+    #[should_panic(expected = "removal index (is 5) should be < len (is 3)")]
+    fn it_panics_on_remove_past_len() {
+        let mut vec: LocalStorageVec<i32, 8> = LocalStorageVec::from([1, 2, 3]);
+        vec.remove(5);
+    }
+
+
+    #[test]
+    #[should_panic(expected = "removal index (is 0) should be < len (is 0)")]
+    fn it_panics_on_remove_from_empty_stack() {
+        let mut vec: LocalStorageVec<i32, 8> = LocalStorageVec::new();
+        vec.remove(0);
+    }
+
+
+    #[test]
+    #[cfg(feature = "nightly")]
+    fn it_extends_one_at_a_time() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::new();
+        vec.extend_reserve(2);
+        assert!(matches!(vec, LocalStorageVec::Stack { .. }));
+        for i in 0..4 {
+            vec.extend_one(i);
+        }
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 4, .. }));
+
+        vec.extend_reserve(6);
+        assert!(matches!(&vec, LocalStorageVec::Heap(v) if v.capacity() >= 10));
+        for i in 4..10 {
+            vec.extend_one(i);
+        }
+        assert_eq!(vec.as_ref(), (0..10).collect::<Vec<_>>());
+    }
+
+
+    #[test]
+    fn it_holds_owned_values() {
+        let mut vec: LocalStorageVec<String, 4> = LocalStorageVec::new();
+        vec.push("a".into());
+        assert_eq!(vec.pop().as_deref(), Some("a"));
+
+        for s in ["b", "c", "d"] {
+            vec.push(s.into());
+        }
+        vec.insert(0, "a".into());
+        assert_eq!(vec.remove(2), "c");
+        assert_eq!(vec.as_ref(), &["a", "b", "d"]);
+    }
+
+
+    #[test]
+    fn it_drops_each_element_once_when_spilling() {
+        let dropped = Rc::new(RefCell::new(Vec::new()));
+        let mut vec: LocalStorageVec<DropCounter, 2> = LocalStorageVec::new();
+        for id in 0..4 {
+            vec.push(DropCounter::new(id, &dropped));
+        }
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert!(dropped.borrow().is_empty());
+
+        drop(vec.remove(1));
+        assert_eq!(*dropped.borrow(), [1]);
+
+        drop(vec);
+        let mut all = dropped.borrow().clone();
+        all.sort();
+        assert_eq!(all, [0, 1, 2, 3]);
+    }
+
+
+    #[test]
+    fn it_iters_owned_values() {
+        let vec: LocalStorageVec<String, 8> = LocalStorageVec::from(["a", "b", "c"].map(String::from));
         let mut iter = vec.into_iter();
-        for item in &mut iter {
-            assert_eq!(item, 0);
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next_back().as_deref(), Some("c"));
+        assert_eq!(iter.collect::<Vec<_>>(), ["a", "b"]);
+
+        let vec: LocalStorageVec<Box<u32>, 2> = LocalStorageVec::from([1, 2, 3].map(Box::new));
+        assert_eq!(vec.into_iter().rev().map(|b| *b).collect::<Vec<_>>(), [3, 2, 1]);
+    }
+
+
+    #[test]
+    fn it_drops_the_rest_of_the_iter() {
+        let dropped = Rc::new(RefCell::new(Vec::new()));
+        let vec: LocalStorageVec<DropCounter, 8> =
+            LocalStorageVec::from([0, 1, 2, 3, 4, 5].map(|id| DropCounter::new(id, &dropped)));
+        let mut iter = vec.into_iter();
+        drop(iter.next());
+        drop(iter.next_back());
+        assert_eq!(*dropped.borrow(), [0, 5]);
+
+        drop(iter);
+        let mut all = dropped.borrow().clone();
+        all.sort();
+        assert_eq!(all, [0, 1, 2, 3, 4, 5]);
+    }
+
+
+    /// An operation applied to both a [LocalStorageVec] and a reference [Vec]
+    #[derive(Debug, Clone)]
+    enum Op {
+        Push(i32),
+        Pop,
+        /// Index modulo `len + 1`
+        Insert(usize, i32),
+        /// Index modulo `len`
+        Remove(usize),
+        /// Keeps the multiples of the divisor
+        Retain(i32),
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            any::<i32>().prop_map(Op::Push),
+            Just(Op::Pop),
+            (any::<usize>(), any::<i32>()).prop_map(|(i, x)| Op::Insert(i, x)),
+            any::<usize>().prop_map(Op::Remove),
+            (1..4).prop_map(Op::Retain),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn it_behaves_like_vec(ops in prop::collection::vec(op(), 0..64)) {
+            let mut vec: LocalStorageVec<i32, 8> = LocalStorageVec::new();
+            let mut reference = Vec::new();
+            for op in ops {
+                match op {
+                    Op::Push(x) => {
+                        vec.push(x);
+                        reference.push(x);
+                    }
+                    Op::Pop => prop_assert_eq!(vec.pop(), reference.pop()),
+                    Op::Insert(i, x) => {
+                        let i = i % (reference.len() + 1);
+                        vec.insert(i, x);
+                        reference.insert(i, x);
+                    }
+                    Op::Remove(_) if reference.is_empty() => {}
+                    Op::Remove(i) => {
+                        let i = i % reference.len();
+                        prop_assert_eq!(vec.remove(i), reference.remove(i));
+                    }
+                    Op::Retain(d) => {
+                        vec.retain(|x| x % d == 0);
+                        reference.retain(|x| x % d == 0);
+                    }
+                }
+                prop_assert_eq!(vec.as_ref(), reference.as_slice());
+            }
         }
-        assert_eq!(iter.next(), None);
     }
 
 
     #[test]
-    fn it_indexes() {
-        let vec: LocalStorageVec<i32, 10> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
-        assert_eq!(vec[1], 1);
-        assert_eq!(vec[..2], [0, 1]);
-        assert_eq!(vec[4..], [4, 5]);
-        assert_eq!(vec[1..3], [1, 2]);
+    fn it_views_full_stacks_as_arrays() {
+        let mut vec: LocalStorageVec<_, 3> = LocalStorageVec::from([1, 2]);
+        assert_eq!(vec.as_full_array(), None);
+        vec.push(3);
+        assert_eq!(vec.as_full_array(), Some(&[1, 2, 3]));
+        vec.push(4);
+        assert_eq!(vec.as_full_array(), None);
+
+        let vec: LocalStorageVec<_, 3> = LocalStorageVec::from(vec![1, 2, 3]);
+        assert_eq!(vec.as_full_array(), None);
     }
 
 
     #[test]
-    fn it_borrowing_iters() {
-        let vec: LocalStorageVec<String, 10> = LocalStorageVec::from([
-            "0".to_owned(),
-            "1".to_owned(),
-            "2".to_owned(),
-            "3".to_owned(),
-            "4".to_owned(),
-            "5".to_owned(),
-        ]);
-        let iter = vec.iter();
-        for _ in iter {}
+    fn it_collects() {
+        let vec: LocalStorageVec<i32, 4> = (0..3).collect();
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+        assert_eq!(vec.as_ref(), &[0, 1, 2]);
+
+        let vec: LocalStorageVec<i32, 4> = (0..10).collect();
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert_eq!(vec.as_ref(), (0..10).collect::<Vec<_>>());
+
+        let mut vec: LocalStorageVec<i32, 4> = (0..3).collect();
+        vec.extend(3..4);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 4, .. }));
+        vec.extend(4..8);
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert_eq!(vec.as_ref(), &[0, 1, 2, 3, 4, 5, 6, 7]);
+    }
+
+
+    #[test]
+    fn it_shrinks_to_the_stack() {
+        let mut vec: LocalStorageVec<u32, 128> = LocalStorageVec::new();
+        vec.extend(0..200);
+        vec.shrink_to_stack();
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+
+        for _ in 0..100 {
+            vec.pop();
+        }
+        vec.shrink_to_stack();
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 100, .. }));
+        assert_eq!(vec.as_ref(), (0..100).collect::<Vec<_>>());
+    }
+
+
+    #[test]
+    fn it_truncates_from_the_back() {
+        let dropped = Rc::new(RefCell::new(Vec::new()));
+        let mut vec: LocalStorageVec<DropCounter, 8> =
+            LocalStorageVec::from([0, 1, 2, 3, 4].map(|id| DropCounter::new(id, &dropped)));
+        vec.truncate(2);
+        assert_eq!(*dropped.borrow(), [4, 3, 2]);
+
+        let dropped = Rc::new(RefCell::new(Vec::new()));
+        let mut vec: LocalStorageVec<DropCounter, 2> =
+            LocalStorageVec::from([0, 1, 2, 3, 4].map(|id| DropCounter::new(id, &dropped)));
+        vec.truncate(1);
+        assert_eq!(*dropped.borrow(), [4, 3, 2, 1]);
+    }
+
+
+    #[test]
+    fn it_compares_across_variants() {
+        let stack: LocalStorageVec<_, 4> = LocalStorageVec::from([1, 2, 3]);
+        let heap: LocalStorageVec<_, 4> = LocalStorageVec::from(vec![1, 2, 3]);
+        assert!(matches!(stack, LocalStorageVec::Stack { .. }));
+        assert!(matches!(heap, LocalStorageVec::Heap(_)));
+        assert_eq!(stack, heap);
+        assert_ne!(stack, LocalStorageVec::from([1, 2]));
+        assert_eq!(format!("{stack:?}"), "[1, 2, 3]");
+    }
+
+
+    #[test]
+    fn it_clones() {
+        let vec: LocalStorageVec<String, 4> = LocalStorageVec::from(["a", "b"].map(String::from));
+        let clone = vec.clone();
+        assert!(matches!(clone, LocalStorageVec::Stack { len: 2, .. }));
+        assert_eq!(clone, vec);
+
+        let vec: LocalStorageVec<String, 8> = LocalStorageVec::from(["a", "b", "c"].map(String::from));
+        let clone = vec.clone();
+        assert!(matches!(clone, LocalStorageVec::Stack { len: 3, .. }));
+        assert_eq!(clone.as_ref(), ["a", "b", "c"]);
         drop(vec);
+        assert_eq!(clone.concat(), "abc");
+
+        let vec: LocalStorageVec<String, 1> = LocalStorageVec::from(["a", "b"].map(String::from));
+        let clone = vec.clone();
+        assert!(matches!(clone, LocalStorageVec::Heap(_)));
+        assert_eq!(clone, vec);
     }
 
 
+    #[cfg(feature = "serde")]
     #[test]
-    fn it_derefs() {
-        use std::ops::{Deref, DerefMut};
-        let vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 128]);
-        let chunks = vec.chunks(4);
-        let slice: &[_] = vec.deref();
+    fn it_round_trips_through_serde() {
+        let vec: LocalStorageVec<u32, 4> = LocalStorageVec::from([1, 2, 3]);
+        let json = serde_json::to_string(&vec).unwrap();
+        assert_eq!(json, "[1,2,3]");
+        let vec: LocalStorageVec<u32, 4> = serde_json::from_str(&json).unwrap();
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+        assert_eq!(vec.as_ref(), &[1, 2, 3]);
+
+        let json = serde_json::to_string(&(0..10).collect::<LocalStorageVec<u32, 4>>()).unwrap();
+        assert_eq!(json, "[0,1,2,3,4,5,6,7,8,9]");
+        let vec: LocalStorageVec<u32, 4> = serde_json::from_str(&json).unwrap();
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert_eq!(vec.as_ref(), (0..10).collect::<Vec<_>>());
+    }
 
-        let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 128]);
-        let chunks = vec.chunks_mut(4);
-        let slice: &mut [_] = vec.deref_mut();
+
+    #[test]
+    fn it_slices_by_bounds() {
+        use std::ops::Bound::{Excluded, Included, Unbounded};
+
+        let vec: LocalStorageVec<_, 8> = LocalStorageVec::from([0, 1, 2, 3, 4]);
+        assert_eq!(vec.slice_bounds(Included(1), Included(3)), &[1, 2, 3]);
+        assert_eq!(vec.slice_bounds(Excluded(1), Excluded(3)), &[2]);
+        assert_eq!(vec.slice_bounds(Included(1), Excluded(3)), &[1, 2]);
+        assert_eq!(vec.slice_bounds(Excluded(1), Unbounded), &[2, 3, 4]);
+        assert_eq!(vec.slice_bounds(Unbounded, Included(1)), &[0, 1]);
+        assert_eq!(vec.slice_bounds(Unbounded, Unbounded), &[0, 1, 2, 3, 4]);
+        assert!(vec.slice_bounds(Excluded(4), Unbounded).is_empty());
+
+        let vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2, 3, 4]);
+        assert_eq!(vec.slice_bounds(Excluded(0), Included(2)), &[1, 2]);
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn it_panics_on_bounds_past_len() {
+        let vec: LocalStorageVec<_, 8> = LocalStorageVec::from([0, 1, 2]);
+        let _ = vec.slice_bounds(std::ops::Bound::Included(1), std::ops::Bound::Included(3));
+    }
+
+
+    #[test]
+    fn it_displays_chars() {
+        let mut vec: LocalStorageVec<char, 4> = "rust".chars().collect();
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 4, .. }));
+        assert_eq!(vec.to_string(), "rust");
+
+        vec.extend(" ✓".chars());
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert_eq!(format!("[{vec}]"), "[rust ✓]");
+        assert_eq!(vec.iter().collect::<String>(), "rust ✓");
+
+        assert_eq!(LocalStorageVec::<char, 4>::new().to_string(), "");
+    }
+
+
+    #[test]
+    fn it_retains_consistently_when_the_predicate_panics() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        fn retain_panicking<const N: usize>(vec: &mut LocalStorageVec<Rc<u32>, N>) {
+            let result = catch_unwind(AssertUnwindSafe(|| {
+                vec.retain(|value| {
+                    assert_ne!(**value, 3, "predicate panics on 3");
+                    **value % 2 == 0
+                })
+            }));
+            assert!(result.is_err());
+            // 1 was removed, 3 and the elements after it were never looked at
+            assert!(vec.iter().map(|value| **value).eq([0, 2, 3, 4, 5]));
+        }
+
+        let values: Vec<Rc<u32>> = (0..6).map(Rc::new).collect();
+        let mut stack: LocalStorageVec<_, 8> = values.iter().cloned().collect();
+        let mut heap: LocalStorageVec<_, 2> = values.iter().cloned().collect();
+        retain_panicking(&mut stack);
+        retain_panicking(&mut heap);
+        // Each element is owned once by either vec, except for the dropped 1s
+        let counts: Vec<_> = values.iter().map(Rc::strong_count).collect();
+        assert_eq!(counts, [3, 1, 3, 3, 3, 3]);
+
+        drop(stack);
+        drop(heap);
+        assert!(values.iter().all(|value| Rc::strong_count(value) == 1));
+    }
+
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn it_counts_spills_and_shrinks() {
+        use crate::{shrink_count, spill_count};
+
+        // Other tests run concurrently and may spill too, so only lower bounds hold
+        let (spills, shrinks) = (spill_count(), shrink_count());
+        for _ in 0..3 {
+            let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1]);
+            vec.push(2);
+            vec.pop();
+            vec.shrink_to_stack();
+        }
+        assert!(spill_count() >= spills + 3);
+        assert!(shrink_count() >= shrinks + 3);
     }
-}
\ No newline at end of file
+}
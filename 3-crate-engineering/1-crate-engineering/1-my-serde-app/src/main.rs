@@ -11,21 +11,126 @@ fn fetch_data() -> String {
     )
 }
 
+/// Blog posts as newline-delimited JSON, one post per line
+fn fetch_ndjson_data() -> String {
+    String::from(
+        r#"
+            { "id": 2, "title": "Ownership" }
+
+            { "id": 3, "title": "Borrowing" }
+        "#,
+    )
+}
+
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 struct BlogPost {
     id: u32,
     title: String,
 }
 
+/// A blog post that was well-formed JSON, but has invalid contents
+#[derive(Debug, PartialEq)]
+enum BlogPostError {
+    /// The `id` field was 0
+    ZeroId,
+    /// The `title` field was empty
+    EmptyTitle,
+}
+
+impl std::error::Error for BlogPostError {}
+
+impl std::fmt::Display for BlogPostError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlogPostError::ZeroId => write!(f, "field `id` must not be 0"),
+            BlogPostError::EmptyTitle => write!(f, "field `title` must not be empty"),
+        }
+    }
+}
+
+impl BlogPost {
+    fn validate(&self) -> std::result::Result<(), BlogPostError> {
+        if self.id == 0 {
+            return Err(BlogPostError::ZeroId);
+        }
+        if self.title.is_empty() {
+            return Err(BlogPostError::EmptyTitle);
+        }
+        Ok(())
+    }
+}
+
+/// Deserializes a blog post and checks that its fields are valid
+fn parse_blog_post(data: &str) -> anyhow::Result<BlogPost> {
+    let post: BlogPost = serde_json::from_str(data)?;
+    post.validate()?;
+    Ok(post)
+}
+
+/// Deserializes newline-delimited JSON, one blog post per line,
+/// yielding a result for every non-blank line
+fn parse_ndjson<R: std::io::BufRead>(reader: R) -> impl Iterator<Item = anyhow::Result<BlogPost>> {
+    reader.lines().filter_map(|line| match line {
+        Ok(line) if line.trim().is_empty() => None,
+        Ok(line) => Some(parse_blog_post(&line)),
+        Err(e) => Some(Err(e.into())),
+    })
+}
+
 fn main() -> anyhow::Result<()> {
     let post: BlogPost = {
         let data = fetch_data();
-        serde_json::from_str(data.as_str())?
+        parse_blog_post(data.as_str())?
     };
     println!("deserialized = {:?}", post);
 
     let post_json: String = serde_json::to_string(&post)?;
     println!("serialized = {:?}", post_json);
 
+    for post in parse_ndjson(fetch_ndjson_data().as_bytes()) {
+        println!("deserialized = {:?}", post?);
+    }
+
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_blog_post() {
+        let post = parse_blog_post(&fetch_data()).unwrap();
+        assert_eq!(post.id, 1);
+        assert_eq!(post.title, "Hello, Rust");
+    }
+
+    #[test]
+    fn test_empty_title() {
+        let err = parse_blog_post(r#"{ "id": 1, "title": "" }"#).unwrap_err();
+        assert_eq!(err.downcast_ref(), Some(&BlogPostError::EmptyTitle));
+    }
+
+    #[test]
+    fn test_zero_id() {
+        let err = parse_blog_post(r#"{ "id": 0, "title": "Hello, Rust" }"#).unwrap_err();
+        assert_eq!(err.downcast_ref(), Some(&BlogPostError::ZeroId));
+    }
+
+    #[test]
+    fn test_parse_ndjson() {
+        let input = concat!(
+            r#"{ "id": 1, "title": "Hello, Rust" }"#,
+            "\n\n",
+            r#"{ "id": 2, "title": "#,
+            "\n",
+            r#"{ "id": 3, "title": "Goodbye, Rust" }"#,
+            "\n",
+        );
+        let results: Vec<_> = parse_ndjson(input.as_bytes()).collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().id, 1);
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().unwrap().id, 3);
+    }
+}
@@ -0,0 +1,30 @@
+use bsn::Bsn;
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+
+/// Benchmark [Bsn::validate] over the fixture files,
+/// which mostly take the fast path for nine ASCII digits,
+/// against the `char` based [Bsn::validate_with] it falls back to
+fn bench_validate(c: &mut Criterion) {
+    let valid: Vec<&str> = include_str!("../valid_bsns.in").lines().collect();
+    let invalid: Vec<&str> = include_str!("../invalid_bsns.in").lines().collect();
+    let weights = Bsn::checksum_weights();
+    let validate_chars = |bsn: &str| Bsn::validate_with(&weights, 11, 9, bsn);
+    let mut group = c.benchmark_group("validate");
+    group.bench_function("valid", |b| {
+        b.iter(|| valid.iter().filter(|bsn| Bsn::validate(black_box(bsn)).is_ok()).count())
+    });
+    group.bench_function("invalid", |b| {
+        b.iter(|| invalid.iter().filter(|bsn| Bsn::validate(black_box(bsn)).is_err()).count())
+    });
+    group.bench_function("valid_chars", |b| {
+        b.iter(|| valid.iter().filter(|bsn| validate_chars(black_box(bsn)).is_ok()).count())
+    });
+    group.bench_function("invalid_chars", |b| {
+        b.iter(|| invalid.iter().filter(|bsn| validate_chars(black_box(bsn)).is_err()).count())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_validate);
+criterion_main!(benches);
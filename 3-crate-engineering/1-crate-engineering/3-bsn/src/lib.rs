@@ -1,14 +1,26 @@
+use std::borrow::Borrow;
 use std::fmt::Display;
+use std::io::BufRead;
+use std::ops::Deref;
+use std::str::FromStr;
 
 use serde::{de::Visitor, Deserialize, Serialize};
 
 #[derive(Debug)]
 /// Error creating BSN
-// TODO: update the enum to make it more descriptive
-// as there can be several reasons for a BSN to not be valid
 pub enum Error {
-    /// The BSN was invalid
-    InvalidBsn,
+    /// The BSN does not consist of exactly nine characters
+    WrongLength { actual: usize },
+    /// The BSN contains a character that is not a digit
+    NonDigitCharacter { index: usize, found: char },
+    /// The BSN does not pass the elfproef
+    ChecksumFailed,
+    /// The BSN consists of a single repeated digit
+    RepeatedDigits,
+    /// The BSN contains a long run of consecutive ascending or descending digits
+    SequentialDigits,
+    /// The BSN could not be read
+    Io(std::io::Error),
 }
 
 impl std::error::Error for Error {}
@@ -16,39 +28,379 @@ impl std::error::Error for Error {}
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Error::InvalidBsn => write!(f, "Invalid BSN number"),
+            Error::WrongLength { actual } => write!(f, "BSN should have 9 digits, but has {actual}"),
+            Error::NonDigitCharacter { index, found } => {
+                write!(f, "BSN contains non-digit character {found:?} at index {index}")
+            }
+            Error::ChecksumFailed => write!(f, "BSN does not pass the elfproef"),
+            Error::RepeatedDigits => write!(f, "BSN consists of a single repeated digit"),
+            Error::SequentialDigits => write!(f, "BSN contains a run of sequential digits"),
+            Error::Io(e) => write!(f, "Could not read BSN: {e}"),
+        }
+    }
+}
+
+impl Error {
+    /// Describes the error without the details taken from the rejected input,
+    /// such as its length or the offending character and its position,
+    /// so that it can end up in logs
+    fn redacted(&self) -> String {
+        match self {
+            Error::WrongLength { .. } => "BSN does not have 9 digits".to_owned(),
+            Error::NonDigitCharacter { .. } => "BSN contains a non-digit character".to_owned(),
+            other => other.to_string(),
         }
     }
 }
 
+/// Turns an [Error] into a deserialization error that does not reveal the
+/// rejected value, which is personal data
+fn de_error<E: serde::de::Error>(err: Error) -> E {
+    E::custom(err.redacted())
+}
+
 /// A valid BSN (burgerservicenummer), a Dutch
 /// personal identification number that is similar
 /// to the US Social Security Number.
 /// More info (Dutch): https://www.rvig.nl/bsn
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct Bsn {
     inner: String,
 }
 
 impl Bsn {
     pub fn try_from_string<B: ToString>(bsn: B) -> Result<Self, Error> {
-        let bsn_str = bsn.to_string();
-        Self::validate(&bsn_str)?;
-        Ok(Bsn { inner: bsn_str })
+        Self::try_from(bsn.to_string())
+    }
+
+    /// Collects the characters of `chars` into a BSN and validates it.
+    /// Unlike [Bsn::validate_chars], the whole iterator is consumed,
+    /// so more than nine characters are rejected as well.
+    pub fn from_chars(chars: impl IntoIterator<Item = char>) -> Result<Self, Error> {
+        Self::try_from(chars.into_iter().collect::<String>())
     }
 
+    /// Like [Bsn::try_from_string], but first strips the separators people
+    /// commonly write BSNs with, so `"9999.98.456"` and `"999 998 456"` are accepted.
+    /// Unlike [LenientBsn], missing leading zeros are not padded back:
+    /// anything but nine digits is rejected with [Error::WrongLength].
+    pub fn parse_lenient<B: ToString>(input: B) -> Result<Self, Error> {
+        Self::try_from(strip_separators(&input.to_string()))
+    }
+
+    /// Wraps a string the caller has already validated, e.g. one read back
+    /// from storage that only ever holds BSNs, skipping the checksum.
+    /// Debug builds check it anyway.
+    ///
+    /// # Safety
+    ///
+    /// `bsn` must pass every check [Bsn::try_from_string] applies.
+    /// The other methods rely on every [Bsn] being nine ASCII digits.
+    pub unsafe fn from_validated_unchecked(bsn: String) -> Self {
+        debug_assert!(Self::check(&bsn).is_ok(), "BSN {bsn} is not valid");
+        Bsn { inner: bsn }
+    }
+
+    /// Generates a uniformly random valid BSN, e.g. for test fixtures.
+    /// The first eight digits are picked freely and the check digit is solved for;
+    /// if no check digit passes the elfproef, the digits are picked again.
+    #[cfg(feature = "rand")]
+    pub fn generate<R: rand::Rng>(rng: &mut R) -> Bsn {
+        let weights = Self::checksum_weights();
+        loop {
+            let mut digits = String::with_capacity(9);
+            let mut sum = 0;
+            for weight in &weights[..8] {
+                let digit: u8 = rng.gen_range(0..10);
+                digits.push((b'0' + digit) as char);
+                sum += weight * digit as i32;
+            }
+            // The check digit has weight -1, so it must equal the sum modulo 11
+            let check = sum % 11;
+            if check == 10 {
+                continue;
+            }
+            digits.push((b'0' + check as u8) as char);
+            if let Ok(bsn) = Bsn::try_from(digits) {
+                return bsn;
+            }
+        }
+    }
+
+    /// The weights of the elfproef: each digit is multiplied by its weight,
+    /// and the sum of the products must be divisible by 11.
+    /// The check digit at the end counts negatively.
+    pub const fn checksum_weights() -> [i32; 9] {
+        [9, 8, 7, 6, 5, 4, 3, 2, -1]
+    }
+
+    /// Checks the length, the digits and the elfproef.
+    ///
+    /// A weighted sum of zero passes the elfproef and is deliberately allowed:
+    /// besides `000000000` it occurs whenever the weighted sum of the first eight
+    /// digits equals the check digit, as in the valid `000000012`.
+    /// Since the check digit is at most 9, the sum is never below -9,
+    /// so zero is the only non-positive sum that can pass.
+    /// Only the all-zeros number is obviously fake; [Bsn::structural_checks] rejects it.
     pub fn validate(bsn: &str) -> Result<(), Error> {
-        if bsn.len() != 9 {
-            return Err(Error::InvalidBsn);
+        match Self::validate_ascii(bsn) {
+            Some(result) => result,
+            None => Self::validate_with(&Self::checksum_weights(), 11, 9, bsn),
+        }
+    }
+
+    /// Whether `bsn` passes [Bsn::validate], for use in conditionals
+    pub fn is_valid(bsn: &str) -> bool {
+        Self::validate(bsn).is_ok()
+    }
+
+    /// Fast path of [Bsn::validate] for the common case of nine ASCII digits,
+    /// doing byte arithmetic instead of decoding `char`s.
+    /// Returns `None` for any other input, which needs the slow path for a precise error.
+    fn validate_ascii(bsn: &str) -> Option<Result<(), Error>> {
+        let bytes: &[u8; 9] = bsn.as_bytes().try_into().ok()?;
+        let mut sum = 0;
+        for (&b, weight) in bytes.iter().zip(Self::checksum_weights()) {
+            let digit = b.wrapping_sub(b'0');
+            if digit > 9 {
+                return None;
+            }
+            sum += weight * digit as i32;
+        }
+        Some(if sum % 11 == 0 {
+            Ok(())
+        } else {
+            Err(Error::ChecksumFailed)
+        })
+    }
+
+    /// Like [Bsn::validate], but reads the digits from `chars` without building a `String`,
+    /// e.g. while parsing a stream. Consumes at most nine characters and leaves the rest
+    /// to the caller, so trailing characters are not reported as [Error::WrongLength].
+    /// Characters are checked as they are read, so a non-digit is reported
+    /// before running out of characters.
+    pub fn validate_chars(chars: impl Iterator<Item = char>) -> Result<(), Error> {
+        let mut actual = 0;
+        let mut sum = 0;
+        for ((index, found), weight) in chars.take(9).enumerate().zip(Self::checksum_weights()) {
+            let digit = found
+                .to_digit(10)
+                .ok_or(Error::NonDigitCharacter { index, found })?;
+            sum += weight * digit as i32;
+            actual += 1;
+        }
+        if actual != 9 {
+            return Err(Error::WrongLength { actual });
         }
-        let digits = bsn.chars().map(|c| c.to_digit(10).unwrap());
-        let sum: i32 = digits.enumerate().map(|(i, d)| if i == 8 { d as i32 } else { (9 - i as i32) * d as i32 }).sum();
         if sum % 11 == 0 {
             Ok(())
         } else {
-            Err(Error::InvalidBsn)
+            Err(Error::ChecksumFailed)
+        }
+    }
+
+    /// Checks that `input` has exactly `len` digits whose weighted sum
+    /// is divisible by `modulus`, so that related identification numbers
+    /// can reuse the elfproef with their own weights.
+    /// Public only so the benchmarks can compare it with the fast path.
+    #[doc(hidden)]
+    pub fn validate_with(weights: &[i32], modulus: i32, len: usize, input: &str) -> Result<(), Error> {
+        let actual = input.chars().count();
+        if actual != len {
+            return Err(Error::WrongLength { actual });
+        }
+        let mut sum = 0;
+        for ((index, found), weight) in input.chars().enumerate().zip(weights) {
+            let digit = found
+                .to_digit(10)
+                .ok_or(Error::NonDigitCharacter { index, found })?;
+            sum += weight * digit as i32;
+        }
+        if sum % modulus == 0 {
+            Ok(())
+        } else {
+            Err(Error::ChecksumFailed)
         }
     }
+
+    /// Validates the BSNs in `reader`, one per line, yielding each result
+    /// together with its 1-based line number. Trailing whitespace is ignored.
+    pub fn validate_reader<R: BufRead>(reader: R) -> impl Iterator<Item = (usize, Result<Bsn, Error>)> {
+        reader.lines().enumerate().map(|(i, line)| {
+            let bsn = line
+                .map_err(Error::Io)
+                .and_then(|line| Bsn::try_from_string(line.trim_end()));
+            (i + 1, bsn)
+        })
+    }
+
+    /// The shortest run of consecutive ascending or descending digits
+    /// that [Bsn::structural_checks] rejects
+    const MIN_SEQUENTIAL_RUN: usize = 6;
+
+    /// Rejects numbers that pass the elfproef but are obviously not real,
+    /// such as a single repeated digit or a run of at least six sequential digits
+    /// anywhere in the number, like the `12345678` in `123456782`.
+    /// The length and the digits are checked first; the elfproef is not.
+    /// With the `strict` feature enabled, this check is applied on construction.
+    pub fn structural_checks(bsn: &str) -> Result<(), Error> {
+        let actual = bsn.chars().count();
+        if actual != 9 {
+            return Err(Error::WrongLength { actual });
+        }
+        if let Some((index, found)) = bsn.chars().enumerate().find(|(_, c)| !c.is_ascii_digit()) {
+            return Err(Error::NonDigitCharacter { index, found });
+        }
+        let bytes = bsn.as_bytes();
+        if bytes.windows(2).all(|pair| pair[0] == pair[1]) {
+            return Err(Error::RepeatedDigits);
+        }
+        let sequential = bytes.windows(Self::MIN_SEQUENTIAL_RUN).any(|run| {
+            run.windows(2).all(|pair| pair[1] == pair[0] + 1) || run.windows(2).all(|pair| pair[0] == pair[1] + 1)
+        });
+        if sequential {
+            return Err(Error::SequentialDigits);
+        }
+        Ok(())
+    }
+
+    /// Validates a BSN without returning early on the first bad character,
+    /// so the running time does not reveal where validation failed.
+    /// Inputs of the wrong length are still rejected immediately.
+    pub fn validate_ct(bsn: &str) -> bool {
+        let bytes = bsn.as_bytes();
+        if bytes.len() != 9 {
+            return false;
+        }
+        let mut non_digit = false;
+        let mut sum: i32 = 0;
+        for (&b, weight) in bytes.iter().zip(Self::checksum_weights()) {
+            let d = b.wrapping_sub(b'0');
+            non_digit |= d > 9;
+            sum += weight * d as i32;
+        }
+        !non_digit & (sum % 11 == 0)
+    }
+
+    /// Runs every check a [Bsn] must pass on construction
+    fn check(bsn: &str) -> Result<(), Error> {
+        Self::validate(bsn)?;
+        #[cfg(feature = "strict")]
+        Self::structural_checks(bsn)?;
+        Ok(())
+    }
+
+    /// The nine digits of the BSN in order, as values `0..=9`
+    pub fn digits(&self) -> impl Iterator<Item = u8> + '_ {
+        self.inner.bytes().map(|b| b - b'0')
+    }
+
+    /// The numeric value of the BSN, used for ordering and integer conversion
+    fn number(&self) -> u32 {
+        self.inner
+            .parse()
+            .expect("a validated BSN consists of nine digits")
+    }
+}
+
+impl TryFrom<String> for Bsn {
+    type Error = Error;
+
+    fn try_from(bsn: String) -> Result<Self, Self::Error> {
+        Self::check(&bsn)?;
+        Ok(Bsn { inner: bsn })
+    }
+}
+
+/// A fixed placeholder BSN that passes validation, including the `strict`
+/// structural checks, so structs deriving [Default] stay valid
+impl Default for Bsn {
+    fn default() -> Self {
+        Bsn {
+            inner: "999999990".to_owned(),
+        }
+    }
+}
+
+/// Displays the nine digits, honoring width, fill and alignment
+impl Display for Bsn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(&self.inner)
+    }
+}
+
+impl FromStr for Bsn {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s.to_owned())
+    }
+}
+
+impl PartialOrd for Bsn {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// BSNs are ordered by their numeric value rather than their textual form
+impl Ord for Bsn {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.number().cmp(&other.number())
+    }
+}
+
+/// The numeric value of the BSN, e.g. for integer database columns.
+/// Leading zeros are lost, so `012345672` becomes `12345672`;
+/// `Bsn::try_from(u64)` pads them back when turning it into a BSN again.
+/// `TryFrom<&Bsn>` is provided through this impl and never fails.
+impl From<&Bsn> for u64 {
+    fn from(bsn: &Bsn) -> Self {
+        bsn.number().into()
+    }
+}
+
+/// Pads the number back to nine digits, so `12345672` becomes `012345672`.
+/// Numbers of more than nine digits are rejected with [Error::WrongLength].
+impl TryFrom<u64> for Bsn {
+    type Error = Error;
+
+    fn try_from(number: u64) -> Result<Self, Self::Error> {
+        Self::try_from(format!("{number:09}"))
+    }
+}
+
+impl From<Bsn> for String {
+    fn from(bsn: Bsn) -> Self {
+        bsn.inner
+    }
+}
+
+/// A JSON string of the nine digits, for building JSON dynamically.
+/// Requires the optional `serde_json` dependency.
+#[cfg(feature = "serde_json")]
+impl From<Bsn> for serde_json::Value {
+    fn from(bsn: Bsn) -> Self {
+        serde_json::Value::String(bsn.inner)
+    }
+}
+
+/// Gives access to the `str` methods of the nine digits
+impl Deref for Bsn {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+/// Allows looking up BSN-keyed maps and sets by `&str`.
+/// This is sound because [Bsn] hashes and compares just like its digits,
+/// and the numeric ordering of nine-digit strings matches their textual ordering.
+impl Borrow<str> for Bsn {
+    fn borrow(&self) -> &str {
+        &self.inner
+    }
 }
 
 impl Serialize for Bsn {
@@ -78,17 +430,262 @@ impl<'de> Deserialize<'de> for Bsn {
             where
                 E: serde::de::Error,
             {
-                Bsn::try_from_string(value).map_err(E::custom)
+                // Only report what kind of error occurred: the rejected value
+                // is personal data and should not end up in logs
+                Bsn::try_from_string(value).map_err(de_error)
+            }
+
+            // The default `invalid_type` errors would include the number
+            fn visit_u64<E>(self, _value: u64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Err(E::invalid_type(serde::de::Unexpected::Other("an integer"), &self))
+            }
+
+            fn visit_i64<E>(self, _value: i64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Err(E::invalid_type(serde::de::Unexpected::Other("an integer"), &self))
+            }
+
+            fn visit_f64<E>(self, _value: f64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Err(E::invalid_type(serde::de::Unexpected::Other("a floating point number"), &self))
             }
         }
 
-        deserializer.deserialize_str(BsnVisitor)
+        // Self-describing formats like JSON report a number of the wrong type
+        // with its value unless the visitor handles it, so let them hand it over
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(BsnVisitor)
+        } else {
+            deserializer.deserialize_str(BsnVisitor)
+        }
+    }
+}
+
+/// (De)serializes a `Vec<Bsn>` as an array of strings, for use with
+/// `#[serde(with = "bsn::vec_bsn")]`. Each element is validated on the
+/// string slice handed out by the deserializer, so only valid BSNs are
+/// copied into a `String` of their own and no per-element `to_string` is needed.
+pub mod vec_bsn {
+    use serde::de::{SeqAccess, Visitor};
+    use serde::{Deserializer, Serializer};
+
+    use crate::{de_error, Bsn};
+
+    pub fn serialize<S>(bsns: &[Bsn], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(bsns.iter().map(|bsn| &bsn.inner))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Bsn>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(VecBsnVisitor)
+    }
+
+    struct VecBsnVisitor;
+
+    impl<'de> Visitor<'de> for VecBsnVisitor {
+        type Value = Vec<Bsn>;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(formatter, "An array of strings representing valid BSNs")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            // Don't trust the size hint with more than a few pages of memory
+            let mut bsns = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+            while let Some(bsn) = seq.next_element_seed(ElementSeed)? {
+                bsns.push(bsn);
+            }
+            Ok(bsns)
+        }
+    }
+
+    struct ElementSeed;
+
+    impl<'de> serde::de::DeserializeSeed<'de> for ElementSeed {
+        type Value = Bsn;
+
+        fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_str(self)
+        }
+    }
+
+    impl<'de> Visitor<'de> for ElementSeed {
+        type Value = Bsn;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(formatter, "A string representing a valid BSN")
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            // As for a single Bsn, keep the rejected value out of the error
+            Bsn::check(value).map_err(de_error)?;
+            Ok(Bsn {
+                inner: value.to_owned(),
+            })
+        }
+
+        fn visit_string<E>(self, value: String) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            Bsn::try_from(value).map_err(de_error)
+        }
+    }
+}
+
+/// (De)serializes a [Bsn] as an object with validity metadata,
+/// `{"bsn": "999998456", "valid": true}`, for use with
+/// `#[serde(with = "bsn::serde_detailed")]` in API responses.
+/// Deserializing only reads the `"bsn"` field and validates it;
+/// since every [Bsn] is valid, `"valid"` is always `true` when serializing.
+pub mod serde_detailed {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::{de_error, Bsn};
+
+    #[derive(Serialize)]
+    struct Detailed<'a> {
+        bsn: &'a str,
+        valid: bool,
+    }
+
+    #[derive(Deserialize)]
+    struct DetailedOwned {
+        bsn: String,
+    }
+
+    pub fn serialize<S>(bsn: &Bsn, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Detailed {
+            bsn: &bsn.inner,
+            valid: true,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Bsn, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let DetailedOwned { bsn } = DetailedOwned::deserialize(deserializer)?;
+        Bsn::try_from(bsn).map_err(de_error)
+    }
+}
+
+/// Removes the separators people commonly use when writing down a BSN:
+/// ASCII whitespace, dots and hyphens
+fn strip_separators(bsn: &str) -> String {
+    bsn.chars()
+        .filter(|c| !(c.is_ascii_whitespace() || *c == '.' || *c == '-'))
+        .collect()
+}
+
+/// A [Bsn] parsed leniently: separators are stripped and
+/// a number of eight digits, which lost its leading zero, is padded back to nine,
+/// so `"9999.98.456"` and `"13579241"` are accepted.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LenientBsn(Bsn);
+
+impl LenientBsn {
+    pub fn into_inner(self) -> Bsn {
+        self.0
+    }
+}
+
+impl FromStr for LenientBsn {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = strip_separators(s);
+        let digits = if digits.len() == 8 && digits.bytes().all(|b| b.is_ascii_digit()) {
+            format!("{digits:0>9}")
+        } else {
+            digits
+        };
+        Bsn::try_from(digits).map(LenientBsn)
+    }
+}
+
+impl Deref for LenientBsn {
+    type Target = Bsn;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// A valid BSN along with the exact text it was entered as,
+/// e.g. `"9999.98.456"`. Serializes to the original text.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BsnRaw {
+    raw: String,
+    bsn: Bsn,
+}
+
+impl BsnRaw {
+    pub fn try_from_string<B: ToString>(raw: B) -> Result<Self, Error> {
+        let raw = raw.to_string();
+        let bsn = Bsn::try_from(strip_separators(&raw))?;
+        Ok(BsnRaw { raw, bsn })
+    }
+
+    /// The text as it was entered
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// The validated BSN
+    pub fn bsn(&self) -> &Bsn {
+        &self.bsn
+    }
+}
+
+impl Serialize for BsnRaw {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.raw)
+    }
+}
+
+impl<'de> Deserialize<'de> for BsnRaw {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let raw = String::deserialize(deserializer)?;
+        BsnRaw::try_from_string(raw).map_err(de_error)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::Bsn;
+    use crate::{Bsn, BsnRaw, Error, LenientBsn};
+    use std::str::FromStr;
 
     #[test]
     fn test_validation() {
@@ -99,6 +696,176 @@ mod tests {
         bsns.for_each(|bsn| assert!(Bsn::validate(bsn).is_err(), "BSN {bsn} invalid, but passed validation"));
     }
 
+    #[test]
+    fn test_is_valid() {
+        let bsns = include_str!("../valid_bsns.in").lines();
+        let bsns = bsns.chain(include_str!("../invalid_bsns.in").lines());
+        bsns.for_each(|bsn| assert_eq!(Bsn::is_valid(bsn), Bsn::validate(bsn).is_ok(), "BSN {bsn}"));
+        assert!(Bsn::is_valid("999998456"));
+        assert!(!Bsn::is_valid("123456789"));
+    }
+
+    #[test]
+    fn test_validate_ascii() {
+        let weights = Bsn::checksum_weights();
+        let bsns = include_str!("../valid_bsns.in").lines();
+        let bsns = bsns.chain(include_str!("../invalid_bsns.in").lines());
+        let bsns = bsns.chain(["99999845é", "9999 8456", "12345678", ""]);
+        for bsn in bsns {
+            let fast = format!("{:?}", Bsn::validate(bsn));
+            let slow = format!("{:?}", Bsn::validate_with(&weights, 11, 9, bsn));
+            assert_eq!(fast, slow, "BSN {bsn}");
+        }
+    }
+
+    #[test]
+    fn test_validate_reader() {
+        let input = std::io::Cursor::new("999998456\n123456789\n000000012  \r\n");
+        let results: Vec<_> = Bsn::validate_reader(input).collect();
+        assert_eq!(results.len(), 3);
+        assert!(matches!(&results[0], (1, Ok(bsn)) if *bsn == Bsn::try_from_string("999998456").unwrap()));
+        assert!(matches!(&results[1], (2, Err(Error::ChecksumFailed))));
+        assert!(matches!(&results[2], (3, Ok(bsn)) if *bsn == Bsn::try_from_string("000000012").unwrap()));
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_generate() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..10_000 {
+            let bsn = Bsn::generate(&mut rng);
+            assert!(Bsn::validate(&bsn).is_ok(), "BSN {bsn} is not valid");
+        }
+
+        let mut a = StdRng::seed_from_u64(7);
+        let mut b = StdRng::seed_from_u64(7);
+        for _ in 0..100 {
+            assert_eq!(Bsn::generate(&mut a), Bsn::generate(&mut b));
+        }
+    }
+
+    #[test]
+    fn test_checksum_weights() {
+        let weights = Bsn::checksum_weights();
+        assert_eq!(weights, [9, 8, 7, 6, 5, 4, 3, 2, -1]);
+
+        let digits = "999998456".bytes().map(|b| (b - b'0') as i32);
+        let sum: i32 = digits.zip(weights).map(|(d, w)| d * w).sum();
+        assert_eq!(sum % 11, 0);
+    }
+
+    #[test]
+    fn test_error_variants() {
+        assert!(matches!(
+            Bsn::try_from_string("12345678X"),
+            Err(Error::NonDigitCharacter { index: 8, found: 'X' })
+        ));
+        assert!(matches!(
+            Bsn::validate("9999 8456"),
+            Err(Error::NonDigitCharacter { index: 4, found: ' ' })
+        ));
+        assert!(matches!(Bsn::validate("13579241"), Err(Error::WrongLength { actual: 8 })));
+        assert!(matches!(Bsn::validate("1234567890"), Err(Error::WrongLength { actual: 10 })));
+        assert!(matches!(Bsn::validate("123456789"), Err(Error::ChecksumFailed)));
+    }
+
+    #[test]
+    fn test_validate_chars() {
+        assert!(Bsn::validate_chars("999998456".chars()).is_ok());
+        assert!(matches!(Bsn::validate_chars("123456789".chars()), Err(Error::ChecksumFailed)));
+        assert!(matches!(
+            Bsn::validate_chars("9999".chars()),
+            Err(Error::WrongLength { actual: 4 })
+        ));
+        assert!(matches!(
+            Bsn::validate_chars("9999x8456".chars()),
+            Err(Error::NonDigitCharacter { index: 4, found: 'x' })
+        ));
+
+        let mut stream = "999998456,013579241".chars();
+        assert!(Bsn::validate_chars(&mut stream).is_ok());
+        assert_eq!(stream.next(), Some(','));
+        assert!(Bsn::validate_chars(&mut stream).is_ok());
+    }
+
+    #[test]
+    fn test_from_chars() {
+        let bsn = Bsn::from_chars("999998456".chars()).unwrap();
+        assert_eq!(bsn, Bsn::try_from_string("999998456").unwrap());
+        assert!(Bsn::from_chars(['0', '1', '3', '5', '7', '9', '2', '4', '1']).is_ok());
+
+        assert!(matches!(Bsn::from_chars("12345".chars()), Err(Error::WrongLength { actual: 5 })));
+        assert!(matches!(
+            Bsn::from_chars("9999984560".chars()),
+            Err(Error::WrongLength { actual: 10 })
+        ));
+        assert!(matches!(
+            Bsn::from_chars("99999845a".chars()),
+            Err(Error::NonDigitCharacter { index: 8, found: 'a' })
+        ));
+    }
+
+    #[test]
+    fn test_validate_with() {
+        // A hypothetical six digit number with weights 6 down to 1, modulo 7
+        let weights = [6, 5, 4, 3, 2, 1];
+        assert!(Bsn::validate_with(&weights, 7, 6, "100001").is_ok());
+        assert!(Bsn::validate_with(&weights, 7, 6, "100002").is_err());
+        assert!(Bsn::validate_with(&weights, 7, 6, "1000010").is_err());
+        assert!(Bsn::validate_with(&Bsn::checksum_weights(), 11, 9, "999998456").is_ok());
+    }
+
+    #[test]
+    fn test_zero_sum() {
+        // 2 * 1 - 2 == 0 and 3 * 1 + 2 * 2 - 7 == 0
+        for bsn in ["000000000", "000000012", "000000127"] {
+            assert!(Bsn::validate(bsn).is_ok(), "BSN {bsn}");
+            assert!(Bsn::validate_ct(bsn), "BSN {bsn}");
+        }
+        assert!(matches!(Bsn::structural_checks("000000000"), Err(Error::RepeatedDigits)));
+        assert!(Bsn::structural_checks("000000012").is_ok());
+
+        // Sums just beside zero, including the negative ones
+        for bsn in ["000000011", "000000013", "000000001", "000000009"] {
+            assert!(matches!(Bsn::validate(bsn), Err(Error::ChecksumFailed)), "BSN {bsn}");
+        }
+    }
+
+    #[test]
+    fn test_structural_checks() {
+        // Every fixture passes the elfproef, so only its structure is rejected
+        for bsn in ["000000000", "123456782", "987654329", "912345603", "387654306", "912345007"] {
+            assert!(Bsn::validate(bsn).is_ok(), "BSN {bsn}");
+        }
+        assert!(matches!(Bsn::structural_checks("000000000"), Err(Error::RepeatedDigits)));
+        assert!(matches!(Bsn::structural_checks("123456782"), Err(Error::SequentialDigits)));
+        assert!(matches!(Bsn::structural_checks("987654329"), Err(Error::SequentialDigits)));
+        assert!(matches!(Bsn::structural_checks("912345603"), Err(Error::SequentialDigits)));
+        assert!(matches!(Bsn::structural_checks("387654306"), Err(Error::SequentialDigits)));
+        // A run of five is still allowed
+        assert!(Bsn::structural_checks("912345007").is_ok());
+        assert!(Bsn::structural_checks("999998456").is_ok());
+        assert!(Bsn::structural_checks("013579241").is_ok());
+
+        assert!(matches!(Bsn::structural_checks(""), Err(Error::WrongLength { actual: 0 })));
+        assert!(matches!(Bsn::structural_checks("abc"), Err(Error::WrongLength { actual: 3 })));
+        assert!(matches!(
+            Bsn::structural_checks("12345678a"),
+            Err(Error::NonDigitCharacter { index: 8, found: 'a' })
+        ));
+    }
+
+    #[test]
+    fn test_validation_ct() {
+        let bsns = include_str!("../valid_bsns.in").lines();
+        bsns.for_each(|bsn| assert_eq!(Bsn::validate_ct(bsn), Bsn::validate(bsn).is_ok(), "BSN {bsn}"));
+
+        let bsns = include_str!("../invalid_bsns.in").lines();
+        bsns.for_each(|bsn| assert_eq!(Bsn::validate_ct(bsn), Bsn::validate(bsn).is_ok(), "BSN {bsn}"));
+    }
+
     #[test]
     fn test_serde() {
         let json = serde_json::to_string(&Bsn::try_from_string("999998456").unwrap()).unwrap();
@@ -107,5 +874,261 @@ mod tests {
         assert_eq!(bsn, Bsn::try_from_string("999998456".to_string()).unwrap());
 
         serde_json::from_str::<Bsn>("\"1112223333\"").unwrap_err();
+
+        let err = serde_json::from_str::<Bsn>("999998456").unwrap_err();
+        assert!(!err.to_string().contains("999998456"), "{err}");
+        let err = serde_json::from_str::<Bsn>("-999998456").unwrap_err();
+        assert!(!err.to_string().contains("999998456"), "{err}");
+        let err = serde_json::from_str::<Bsn>("999998456.0").unwrap_err();
+        assert!(!err.to_string().contains("999998456"), "{err}");
+    }
+
+    #[test]
+    fn test_from_validated_unchecked() {
+        // SAFETY: `999998456` is a valid BSN
+        let bsn = unsafe { Bsn::from_validated_unchecked("999998456".to_owned()) };
+        assert_eq!(bsn, Bsn::try_from_string("999998456").unwrap());
+    }
+
+    #[test]
+    fn test_default() {
+        let bsn = Bsn::default();
+        assert!(Bsn::validate(&bsn.to_string()).is_ok());
+        assert!(Bsn::structural_checks(&bsn.to_string()).is_ok());
+        assert_eq!(Bsn::try_from_string("999999990").unwrap(), bsn);
+    }
+
+    #[test]
+    fn test_serde_error_hides_input() {
+        let err = serde_json::from_str::<Bsn>("\"123456789\"").unwrap_err();
+        assert!(!err.to_string().contains("123456789"), "{err}");
+        let err = serde_json::from_str::<BsnRaw>("\"1234.56.789\"").unwrap_err();
+        assert!(!err.to_string().contains("1234.56.789"), "{err}");
+
+        let err = serde_json::from_str::<Bsn>("\"1234X6789\"").unwrap_err();
+        assert!(!err.to_string().contains('X'), "{err}");
+        assert!(!err.to_string().contains("index"), "{err}");
+        assert!(err.to_string().contains("non-digit"), "{err}");
+        let err = serde_json::from_str::<Bsn>("\"12345678901234567\"").unwrap_err();
+        assert!(!err.to_string().contains("17"), "{err}");
+        let err = serde_json::from_str::<BsnRaw>("\"1234.5X.789\"").unwrap_err();
+        assert!(!err.to_string().contains('X'), "{err}");
+        let err = serde_json::from_str::<Vec<Bsn>>("[\"999998456\", \"99999845Y\"]").unwrap_err();
+        assert!(!err.to_string().contains('Y'), "{err}");
+    }
+
+    #[test]
+    fn test_display() {
+        let bsn = Bsn::try_from_string("999998456").unwrap();
+        assert_eq!(format!("{bsn}"), "999998456");
+        assert_eq!(format!("{bsn:>12}"), "   999998456");
+        assert_eq!(format!("{bsn:<12}|"), "999998456   |");
+        assert_eq!(format!("{bsn:*^13}"), "**999998456**");
+    }
+
+    #[test]
+    fn test_from_str() {
+        let bsn: Bsn = "999998456".parse().unwrap();
+        assert_eq!(bsn, Bsn::try_from_string("999998456").unwrap());
+        assert!(Bsn::from_str("9999.98.456").is_err());
+        assert!(Bsn::from_str("13579241").is_err());
+
+        let lenient: LenientBsn = "9999.98.456".parse().unwrap();
+        assert_eq!(*lenient, bsn);
+        let lenient: LenientBsn = "9999 98-456".parse().unwrap();
+        assert_eq!(lenient.into_inner(), bsn);
+        let lenient: LenientBsn = "13579241".parse().unwrap();
+        assert_eq!(*lenient, Bsn::try_from_string("013579241").unwrap());
+        assert!(LenientBsn::from_str("1234.56.789").is_err());
+        assert!(matches!(LenientBsn::from_str("9998456"), Err(Error::WrongLength { actual: 7 })));
+        assert!(matches!(LenientBsn::from_str(""), Err(Error::WrongLength { actual: 0 })));
+        assert!(matches!(LenientBsn::from_str(" .- "), Err(Error::WrongLength { actual: 0 })));
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        let bsn = Bsn::try_from_string("999998456").unwrap();
+        assert_eq!(Bsn::parse_lenient("9999.98.456").unwrap(), bsn);
+        assert_eq!(Bsn::parse_lenient("999 998 456").unwrap(), bsn);
+        assert_eq!(Bsn::parse_lenient("9999-98-456").unwrap().to_string(), "999998456");
+
+        let bsn = Bsn::parse_lenient("0135.79.241").unwrap();
+        assert_eq!(bsn.to_string(), "013579241");
+
+        assert!(matches!(Bsn::parse_lenient("1357.92.41"), Err(Error::WrongLength { actual: 8 })));
+        assert!(matches!(Bsn::parse_lenient("9999.98.4560"), Err(Error::WrongLength { actual: 10 })));
+        assert!(matches!(Bsn::parse_lenient(""), Err(Error::WrongLength { actual: 0 })));
+        assert!(matches!(Bsn::parse_lenient(" \t. - "), Err(Error::WrongLength { actual: 0 })));
+        assert!(Bsn::try_from_string("9999.98.456").is_err());
+    }
+
+    #[test]
+    fn test_serde_vec_bsn() {
+        #[derive(Debug, serde::Serialize, serde::Deserialize)]
+        struct Registry {
+            #[serde(with = "crate::vec_bsn")]
+            bsns: Vec<Bsn>,
+        }
+
+        // `strict` rejects some of the fixtures, like the sequential `123456782`
+        let valid = include_str!("../valid_bsns.in").lines().filter(|bsn| Bsn::check(bsn).is_ok());
+        let valid: Vec<&str> = valid.cycle().take(1000).collect();
+        let json = format!(r#"{{"bsns":{}}}"#, serde_json::to_string(&valid).unwrap());
+        let registry: Registry = serde_json::from_str(&json).unwrap();
+        assert_eq!(registry.bsns.len(), 1000);
+        for (bsn, expected) in registry.bsns.iter().zip(&valid) {
+            assert!(Bsn::validate(bsn).is_ok(), "BSN {bsn} is not valid");
+            assert_eq!(&**bsn, *expected);
+        }
+        assert_eq!(serde_json::to_string(&registry).unwrap(), json);
+
+        let err = serde_json::from_str::<Registry>(r#"{"bsns":["999998456","123456789"]}"#).unwrap_err();
+        assert!(!err.to_string().contains("123456789"), "{err}");
+    }
+
+    #[test]
+    fn test_serde_detailed() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Response {
+            #[serde(with = "crate::serde_detailed")]
+            person: Bsn,
+        }
+
+        let response = Response {
+            person: Bsn::try_from_string("999998456").unwrap(),
+        };
+        let json = serde_json::to_string(&response).unwrap();
+        assert_eq!(json, r#"{"person":{"bsn":"999998456","valid":true}}"#);
+        assert_eq!(serde_json::from_str::<Response>(&json).unwrap(), response);
+
+        let without_metadata: Response = serde_json::from_str(r#"{"person":{"bsn":"999998456"}}"#).unwrap();
+        assert_eq!(without_metadata, response);
+        serde_json::from_str::<Response>(r#"{"person":{"bsn":"123456789","valid":true}}"#).unwrap_err();
+    }
+
+    #[test]
+    fn test_serde_borrowed() {
+        #[derive(serde::Serialize)]
+        struct Person<'a> {
+            bsn: &'a Bsn,
+        }
+
+        let bsn = Bsn::try_from_string("999998456").unwrap();
+        assert_eq!(serde_json::to_string(&&bsn).unwrap(), "\"999998456\"");
+        let person = Person { bsn: &bsn };
+        assert_eq!(serde_json::to_string(&person).unwrap(), r#"{"bsn":"999998456"}"#);
+    }
+
+    #[test]
+    fn test_serde_raw() {
+        let raw = BsnRaw::try_from_string("9999.98.456").unwrap();
+        assert_eq!(raw.bsn(), &Bsn::try_from_string("999998456").unwrap());
+
+        let json = serde_json::to_string(&raw).unwrap();
+        assert_eq!(json, "\"9999.98.456\"");
+        let deserialized: BsnRaw = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, raw);
+        assert_eq!(deserialized.raw(), "9999.98.456");
+
+        serde_json::from_str::<BsnRaw>("\"1234.56.789\"").unwrap_err();
+    }
+
+    #[test]
+    fn test_ord() {
+        let small = Bsn::try_from_string("013579241").unwrap();
+        let large = Bsn::try_from_string("100000009").unwrap();
+        assert!(small < large);
+        assert_eq!(large.cmp(&large.clone()), std::cmp::Ordering::Equal);
+
+        let mut bsns = vec![
+            Bsn::try_from_string("999998456").unwrap(),
+            large.clone(),
+            Bsn::try_from_string("000000012").unwrap(),
+            small.clone(),
+        ];
+        bsns.sort();
+        let sorted: Vec<String> = bsns.into_iter().map(String::from).collect();
+        assert_eq!(sorted, ["000000012", "013579241", "100000009", "999998456"]);
+    }
+
+    #[test]
+    fn test_try_from_string() {
+        let bsn = Bsn::try_from(String::from("999998456")).unwrap();
+        assert_eq!(bsn, Bsn::try_from_string("999998456").unwrap());
+        assert!(Bsn::try_from(String::from("123456789")).is_err());
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_into_json_value() {
+        let value = serde_json::Value::from(Bsn::try_from_string("013579241").unwrap());
+        assert_eq!(value, serde_json::Value::String("013579241".to_owned()));
+        assert_eq!(serde_json::json!({ "bsn": value }).to_string(), r#"{"bsn":"013579241"}"#);
+    }
+
+    #[test]
+    fn test_deref() {
+        let bsn = Bsn::try_from_string("999998456").unwrap();
+        assert!(bsn.starts_with("99"));
+        assert_eq!(&bsn[6..], "456");
+        assert_eq!(bsn, Bsn::try_from_string("999998456").unwrap());
+    }
+
+    #[test]
+    fn test_borrow_str() {
+        use std::collections::HashMap;
+        use std::hash::BuildHasher;
+
+        let bsn = Bsn::try_from_string("999998456").unwrap();
+        let mut names = HashMap::new();
+        names.insert(bsn.clone(), "Jan");
+        assert_eq!(names.get("999998456"), Some(&"Jan"));
+        assert_eq!(names.get(&bsn), Some(&"Jan"));
+        assert_eq!(names.get("013579241"), None);
+
+        let hasher = names.hasher();
+        assert_eq!(hasher.hash_one(&bsn), hasher.hash_one("999998456"));
+    }
+
+    #[test]
+    fn test_digits() {
+        let bsn = Bsn::try_from_string("013579241").unwrap();
+        assert_eq!(bsn.digits().collect::<Vec<_>>(), [0, 1, 3, 5, 7, 9, 2, 4, 1]);
+    }
+
+    #[test]
+    fn test_into_u64() {
+        let bsn = Bsn::try_from_string("013579241").unwrap();
+        assert_eq!(u64::from(&bsn), 13_579_241);
+        // The blanket `TryFrom` impl must keep working for generic callers
+        #[allow(clippy::unnecessary_fallible_conversions)]
+        let number = u64::try_from(&bsn).unwrap();
+        assert_eq!(number, 13_579_241);
+        assert_eq!(u64::from(&Bsn::try_from_string("999998456").unwrap()), 999_998_456);
+    }
+
+    #[test]
+    fn test_hash_across_constructors() {
+        use std::collections::hash_map::RandomState;
+        use std::hash::BuildHasher;
+
+        let hasher = RandomState::new();
+        let from_string = Bsn::try_from_string("013579241").unwrap();
+        let from_str = Bsn::from_str("013579241").unwrap();
+        let from_u64 = Bsn::try_from(13_579_241u64).unwrap();
+        assert_eq!(from_string, from_str);
+        assert_eq!(from_string, from_u64);
+        assert_eq!(hasher.hash_one(&from_string), hasher.hash_one(&from_str));
+        assert_eq!(hasher.hash_one(&from_string), hasher.hash_one(&from_u64));
+
+        assert_eq!(Bsn::try_from(u64::from(&from_u64)).unwrap(), from_u64);
+        assert!(matches!(Bsn::try_from(1_000_000_000u64), Err(Error::WrongLength { actual: 10 })));
+    }
+
+    #[test]
+    fn test_into_string() {
+        let bsn = Bsn::try_from_string("999998456").unwrap();
+        let s: String = bsn.into();
+        assert_eq!(s, "999998456");
     }
 }
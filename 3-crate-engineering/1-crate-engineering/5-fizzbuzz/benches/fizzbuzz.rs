@@ -1,5 +1,5 @@
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
-use exercise_b_3::fizz_buzz;
+use exercise_b_3::{fizz_buzz, FizzBuzzRules};
 
 /// Benchmark [fizz_buzz] with several inputs
 /// 1 and 2, 16 and 113 are not divisible by 3 nor 5
@@ -18,5 +18,30 @@ fn bench_fizz_buzz(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_fizz_buzz);
+/// Compare [FizzBuzzRules::apply_batch] against calling [FizzBuzzRules::apply] for each number
+fn bench_apply_batch(c: &mut Criterion) {
+    let rules = FizzBuzzRules::default();
+    let mut group = c.benchmark_group("apply_batch");
+    group.bench_function("apply", |b| {
+        b.iter(|| {
+            let mut result = String::new();
+            let mut out = Vec::new();
+            for i in 1..=10_000 {
+                rules.apply(i, &mut result);
+                out.push(result.clone());
+            }
+            out
+        })
+    });
+    group.bench_function("apply_batch", |b| {
+        b.iter(|| {
+            let mut out = Vec::new();
+            rules.apply_batch(1..=10_000, &mut out);
+            out
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_fizz_buzz, bench_apply_batch);
 criterion_main!(benches);
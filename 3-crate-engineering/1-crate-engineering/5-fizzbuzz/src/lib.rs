@@ -1,14 +1,228 @@
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+use std::sync::OnceLock;
+
+/// Writes the FizzBuzz token for `i` to `result`, using the default 3/5 [FizzBuzzRules]
 pub fn fizz_buzz(i: u32, result: &mut String) {
-    result.clear();
-    match (i % 3 == 0, i % 5 == 0) {
-        (true, true) => result.push_str("FizzBuzz"),
-        (true, false) => result.push_str("Fizz"),
-        (false, true) => result.push_str("Buzz"),
-        (false, false) => {
-            use std::fmt::Write;
-            write!(result, "{}", i).unwrap();
-        },
+    static DEFAULT_RULES: OnceLock<FizzBuzzRules> = OnceLock::new();
+    DEFAULT_RULES.get_or_init(FizzBuzzRules::default).apply(i, result);
+}
+
+/// The kind of FizzBuzz token a number is rendered as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FizzBuzzKind {
+    Fizz,
+    Buzz,
+    FizzBuzz,
+    Number,
+}
+
+/// Classifies `i` by the classic 3/5 rules, without rendering it
+pub const fn classify(i: u32) -> FizzBuzzKind {
+    match (i.is_multiple_of(3), i.is_multiple_of(5)) {
+        (true, true) => FizzBuzzKind::FizzBuzz,
+        (true, false) => FizzBuzzKind::Fizz,
+        (false, true) => FizzBuzzKind::Buzz,
+        (false, false) => FizzBuzzKind::Number,
+    }
+}
+
+/// Writes the FizzBuzz token for `i` to `out`, without an intermediate `String`
+pub fn write_fizz_buzz<W: std::fmt::Write>(i: u32, out: &mut W) -> std::fmt::Result {
+    match classify(i) {
+        FizzBuzzKind::FizzBuzz => out.write_str("FizzBuzz"),
+        FizzBuzzKind::Fizz => out.write_str("Fizz"),
+        FizzBuzzKind::Buzz => out.write_str("Buzz"),
+        FizzBuzzKind::Number => write_number(i, out),
+    }
+}
+
+/// Appends the decimal representation of `i` to `result`
+fn push_number(i: u32, result: &mut String) {
+    write_number(i, result).expect("writing to a String cannot fail");
+}
+
+/// Writes the decimal representation of `i` to `out`
+#[cfg(not(feature = "itoa"))]
+fn write_number<W: std::fmt::Write>(i: u32, out: &mut W) -> std::fmt::Result {
+    write!(out, "{}", i)
+}
+
+/// Writes the decimal representation of `i` to `out`,
+/// bypassing the formatting machinery
+#[cfg(feature = "itoa")]
+fn write_number<W: std::fmt::Write>(i: u32, out: &mut W) -> std::fmt::Result {
+    out.write_str(itoa::Buffer::new().format(i))
+}
+
+/// A single FizzBuzz value, displayed as its FizzBuzz token
+pub struct FizzBuzz(pub u32);
+
+impl Display for FizzBuzz {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_fizz_buzz(self.0, f)
+    }
+}
+
+/// Produces the FizzBuzz output for a range of numbers,
+/// reusing a single internal buffer for every value.
+///
+/// Each string returned by [FizzBuzzBuf::next] borrows that buffer and is
+/// only valid until the next call, which is why this type cannot implement
+/// [Iterator]: an iterator's items may not borrow from the iterator itself.
+pub struct FizzBuzzBuf {
+    range: RangeInclusive<u32>,
+    buf: String,
+}
+
+impl FizzBuzzBuf {
+    pub fn new(range: RangeInclusive<u32>) -> Self {
+        Self {
+            range,
+            buf: String::new(),
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<&str> {
+        let i = self.range.next()?;
+        fizz_buzz(i, &mut self.buf);
+        Some(&self.buf)
+    }
+}
+
+/// A set of FizzBuzz rules: every rule whose divisor divides a number
+/// contributes its label, in order. Numbers matching no rule are written as is.
+///
+/// Each rule has a priority; labels are concatenated in ascending order of
+/// priority, and rules with equal priority keep their insertion order.
+pub struct FizzBuzzRules {
+    rules: Vec<Rule>,
+}
+
+/// A single rule: `label` is written for multiples of `divisor`
+struct Rule {
+    divisor: u32,
+    label: String,
+    priority: u32,
+}
+
+impl FizzBuzzRules {
+    /// Creates rules that all share the same priority,
+    /// so labels are concatenated in insertion order.
+    /// Panics if a divisor is zero.
+    pub fn new(rules: Vec<(u32, String)>) -> Self {
+        Self::with_priorities(
+            rules
+                .into_iter()
+                .map(|(divisor, label)| (divisor, label, 0))
+                .collect(),
+        )
+    }
+
+    /// Creates rules from `(divisor, label, priority)` triples.
+    /// Panics if a divisor is zero.
+    pub fn with_priorities(rules: Vec<(u32, String, u32)>) -> Self {
+        assert!(
+            rules.iter().all(|&(divisor, _, _)| divisor != 0),
+            "divisors must be non-zero"
+        );
+        let mut rules: Vec<Rule> = rules
+            .into_iter()
+            .map(|(divisor, label, priority)| Rule {
+                divisor,
+                label,
+                priority,
+            })
+            .collect();
+        rules.sort_by_key(|rule| rule.priority);
+        Self { rules }
+    }
+
+    pub fn apply(&self, i: u32, result: &mut String) {
+        result.clear();
+        for rule in &self.rules {
+            if i.is_multiple_of(rule.divisor) {
+                result.push_str(&rule.label);
+            }
+        }
+        if result.is_empty() {
+            push_number(i, result);
+        }
+    }
+
+    /// Applies the rules to every number in `range`, appending the results to `out`.
+    /// Rather than dividing each number by every divisor, this keeps track of
+    /// each rule's remainder and advances it as the range is walked.
+    pub fn apply_batch(&self, range: RangeInclusive<u32>, out: &mut Vec<String>) {
+        let start = *range.start();
+        let mut remainders: Vec<u32> = self.rules.iter().map(|rule| start % rule.divisor).collect();
+        out.reserve(range.size_hint().0);
+        for i in range {
+            let mut result = String::new();
+            for (rule, remainder) in self.rules.iter().zip(&mut remainders) {
+                if *remainder == 0 {
+                    result.push_str(&rule.label);
+                }
+                *remainder += 1;
+                if *remainder == rule.divisor {
+                    *remainder = 0;
+                }
+            }
+            if result.is_empty() {
+                push_number(i, &mut result);
+            }
+            out.push(result);
+        }
+    }
+}
+
+/// A language to play FizzBuzz in, selecting the words of the default rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    German,
+    Dutch,
+}
+
+impl Language {
+    /// The words for multiples of 3 and 5, respectively
+    pub fn words(self) -> (&'static str, &'static str) {
+        match self {
+            Language::English => ("Fizz", "Buzz"),
+            Language::German => ("Zisch", "Summ"),
+            Language::Dutch => ("Bruis", "Zoem"),
+        }
+    }
+}
+
+impl FizzBuzzRules {
+    /// The default 3/5 rules, with the words of `lang`
+    pub fn for_language(lang: Language) -> Self {
+        let (fizz, buzz) = lang.words();
+        Self::new(vec![(3, fizz.to_owned()), (5, buzz.to_owned())])
+    }
+}
+
+impl Default for FizzBuzzRules {
+    fn default() -> Self {
+        Self::for_language(Language::English)
+    }
+}
+
+/// Counts how often each kind of output occurs in `range`,
+/// returned as (Fizz, Buzz, FizzBuzz, plain number)
+pub fn fizz_buzz_counts(range: RangeInclusive<u32>) -> (u32, u32, u32, u32) {
+    let mut counts = (0, 0, 0, 0);
+    for i in range {
+        match classify(i) {
+            FizzBuzzKind::FizzBuzz => counts.2 += 1,
+            FizzBuzzKind::Fizz => counts.0 += 1,
+            FizzBuzzKind::Buzz => counts.1 += 1,
+            FizzBuzzKind::Number => counts.3 += 1,
+        }
     }
+    counts
 }
 
 #[cfg(test)]
@@ -28,4 +242,164 @@ mod tests {
             assert_eq!(result, line);
         }
     }
+
+    #[test]
+    fn test_numbers_match_fmt() {
+        let mut result = String::new();
+        for i in 1..=1000 {
+            fizz_buzz(i, &mut result);
+            if i % 3 != 0 && i % 5 != 0 {
+                assert_eq!(result, format!("{}", i));
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_fizz_buzz() {
+        let mut result = String::from("15: ");
+        write_fizz_buzz(15, &mut result).unwrap();
+        assert_eq!(result, "15: FizzBuzz");
+
+        /// Counts the bytes written instead of storing them
+        struct Counter(usize);
+
+        impl std::fmt::Write for Counter {
+            fn write_str(&mut self, s: &str) -> std::fmt::Result {
+                self.0 += s.len();
+                Ok(())
+            }
+        }
+
+        let mut counter = Counter(0);
+        for i in 1..=15 {
+            write_fizz_buzz(i, &mut counter).unwrap();
+        }
+        let expected: usize = include_str!("../fizzbuzz.out").lines().take(15).map(str::len).sum();
+        assert_eq!(counter.0, expected);
+    }
+
+    #[test]
+    fn test_classify() {
+        const FIFTEEN: FizzBuzzKind = classify(15);
+        assert_eq!(FIFTEEN, FizzBuzzKind::FizzBuzz);
+        assert_eq!(classify(0), FizzBuzzKind::FizzBuzz);
+        assert_eq!(classify(9), FizzBuzzKind::Fizz);
+        assert_eq!(classify(25), FizzBuzzKind::Buzz);
+        assert_eq!(classify(13), FizzBuzzKind::Number);
+        assert_eq!(classify(1), FizzBuzzKind::Number);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(format!("{}", FizzBuzz(1)), "1");
+        assert_eq!(format!("{}", FizzBuzz(9)), "Fizz");
+        assert_eq!(format!("{}", FizzBuzz(10)), "Buzz");
+        assert_eq!(format!("{}", FizzBuzz(15)), "FizzBuzz");
+        assert_eq!(format!("[{}]", FizzBuzz(98)), "[98]");
+    }
+
+    #[test]
+    fn test_fizz_buzz_buf() {
+        let expected_output = include_str!("../fizzbuzz.out");
+        let mut expected_lines = expected_output.lines();
+
+        let mut fizz_buzz_buf = FizzBuzzBuf::new(1..=15);
+        while let Some(current) = fizz_buzz_buf.next() {
+            assert_eq!(Some(current), expected_lines.next());
+        }
+        assert_eq!(expected_lines.next(), Some("16"));
+        assert_eq!(fizz_buzz_buf.next(), None);
+    }
+
+    #[test]
+    fn test_rules() {
+        let rules = FizzBuzzRules::default();
+        let mut result = String::new();
+        for (i, line) in include_str!("../fizzbuzz.out").lines().enumerate() {
+            rules.apply(i as u32 + 1, &mut result);
+            assert_eq!(result, line);
+        }
+
+        let rules = FizzBuzzRules::new(vec![
+            (3, "Fizz".to_owned()),
+            (5, "Buzz".to_owned()),
+            (7, "Bazz".to_owned()),
+        ]);
+        rules.apply(21, &mut result);
+        assert_eq!(result, "FizzBazz");
+        rules.apply(105, &mut result);
+        assert_eq!(result, "FizzBuzzBazz");
+        rules.apply(22, &mut result);
+        assert_eq!(result, "22");
+    }
+
+    #[test]
+    fn test_for_language() {
+        let mut result = String::new();
+        for (lang, fizz, buzz) in [
+            (Language::English, "Fizz", "Buzz"),
+            (Language::German, "Zisch", "Summ"),
+            (Language::Dutch, "Bruis", "Zoem"),
+        ] {
+            let rules = FizzBuzzRules::for_language(lang);
+            rules.apply(9, &mut result);
+            assert_eq!(result, fizz);
+            rules.apply(10, &mut result);
+            assert_eq!(result, buzz);
+            rules.apply(30, &mut result);
+            assert_eq!(result, format!("{fizz}{buzz}"));
+            rules.apply(7, &mut result);
+            assert_eq!(result, "7");
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_divisor() {
+        FizzBuzzRules::new(vec![(3, "Fizz".to_owned()), (0, "Zero".to_owned())]);
+    }
+
+    #[test]
+    fn test_apply_batch() {
+        let rules = FizzBuzzRules::new(vec![
+            (3, "Fizz".to_owned()),
+            (5, "Buzz".to_owned()),
+            (7, "Bazz".to_owned()),
+        ]);
+        for range in [1..=1000, 0..=20, 98..=211] {
+            let mut batch = Vec::new();
+            rules.apply_batch(range.clone(), &mut batch);
+
+            let mut result = String::new();
+            let expected: Vec<String> = range
+                .map(|i| {
+                    rules.apply(i, &mut result);
+                    result.clone()
+                })
+                .collect();
+            assert_eq!(batch, expected);
+        }
+    }
+
+    #[test]
+    fn test_rule_priority() {
+        let rules = FizzBuzzRules::with_priorities(vec![
+            (3, "Fizz".to_owned(), 2),
+            (5, "Buzz".to_owned(), 1),
+        ]);
+        let mut result = String::new();
+        rules.apply(15, &mut result);
+        assert_eq!(result, "BuzzFizz");
+        rules.apply(9, &mut result);
+        assert_eq!(result, "Fizz");
+
+        let mut batch = Vec::new();
+        rules.apply_batch(14..=15, &mut batch);
+        assert_eq!(batch, ["14", "BuzzFizz"]);
+    }
+
+    #[test]
+    fn test_fizz_buzz_counts() {
+        assert_eq!(fizz_buzz_counts(1..=15), (4, 2, 1, 8));
+    }
 }
\ No newline at end of file